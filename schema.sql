@@ -3,8 +3,83 @@ DROP TABLE IF EXISTS users;
 CREATE TABLE
     IF NOT EXISTS users (
         id integer PRIMARY KEY AUTOINCREMENT,
-        username text NOT NULL,
-        password_hash text NOT NULL
+        username text NOT NULL UNIQUE,
+        password_hash text NOT NULL,
+        disabled_at text,
+        last_login_at text,
+        password_change_required_at text,
+        count_on_close_at text
+    );
+
+DROP TABLE IF EXISTS user_identities;
+
+CREATE TABLE
+    IF NOT EXISTS user_identities (
+        id integer PRIMARY KEY AUTOINCREMENT,
+        user integer NOT NULL,
+        provider text NOT NULL,
+        subject text NOT NULL,
+        UNIQUE (provider, subject)
+    );
+
+DROP TABLE IF EXISTS user_roles;
+
+CREATE TABLE
+    IF NOT EXISTS user_roles (
+        user integer NOT NULL,
+        role text NOT NULL,
+        PRIMARY KEY (user, role)
+    );
+
+DROP TABLE IF EXISTS api_tokens;
+
+CREATE TABLE
+    IF NOT EXISTS api_tokens (
+        id integer PRIMARY KEY AUTOINCREMENT,
+        user integer NOT NULL,
+        name text NOT NULL,
+        token_hash text NOT NULL UNIQUE,
+        scopes text NOT NULL,
+        created_at text NOT NULL,
+        last_used_at text,
+        revoked_at text
+    );
+
+DROP TABLE IF EXISTS invites;
+
+CREATE TABLE
+    IF NOT EXISTS invites (
+        id integer PRIMARY KEY AUTOINCREMENT,
+        code text NOT NULL UNIQUE,
+        max_uses integer,
+        uses integer NOT NULL DEFAULT 0,
+        expires text,
+        created_by integer NOT NULL,
+        created_at text NOT NULL
+    );
+
+DROP TABLE IF EXISTS invite_uses;
+
+CREATE TABLE
+    IF NOT EXISTS invite_uses (
+        id integer PRIMARY KEY AUTOINCREMENT,
+        invite integer NOT NULL,
+        user integer NOT NULL UNIQUE,
+        used_at text NOT NULL
+    );
+
+DROP TABLE IF EXISTS audit_events;
+
+CREATE TABLE
+    IF NOT EXISTS audit_events (
+        id integer PRIMARY KEY AUTOINCREMENT,
+        actor integer,
+        action text NOT NULL,
+        target_user integer,
+        target_ticket integer,
+        detail text,
+        ip text,
+        created_at text NOT NULL
     );
 
 DROP TABLE IF EXISTS user_tickets;
@@ -15,9 +90,41 @@ CREATE TABLE
         def integer NOT NULL,
         user integer NOT NULL,
         qr text NOT NULL,
-        usages integer DEFAULT 0
+        single_fare integer,
+        replaces integer,
+        notes text,
+        archived_at text,
+        deleted_at text
+    );
+
+CREATE INDEX IF NOT EXISTS user_tickets_replaces ON user_tickets (replaces);
+
+DROP TABLE IF EXISTS idempotency_keys;
+
+CREATE TABLE
+    IF NOT EXISTS idempotency_keys (
+        user integer NOT NULL,
+        key text NOT NULL,
+        ticket integer NOT NULL,
+        response text,
+        created_at text NOT NULL,
+        PRIMARY KEY (user, key)
     );
 
+DROP TABLE IF EXISTS journeys;
+
+CREATE TABLE
+    IF NOT EXISTS journeys (
+        id integer PRIMARY KEY AUTOINCREMENT,
+        ticket integer NOT NULL,
+        taken_at text NOT NULL,
+        route text,
+        stop text,
+        notes text
+    );
+
+CREATE INDEX IF NOT EXISTS journeys_ticket ON journeys (ticket, taken_at);
+
 DROP TABLE IF EXISTS ticket_defs;
 
 CREATE TABLE
@@ -25,22 +132,30 @@ CREATE TABLE
         id integer PRIMARY KEY AUTOINCREMENT,
         title text NOT NULL,
         price integer NOT NULL,
+        currency text NOT NULL DEFAULT 'GBP',
         start text NOT NULL,
-        expiry text NOT NULL
+        expiry text NOT NULL,
+        retired_at text,
+        mode text NOT NULL DEFAULT 'bus',
+        passenger text NOT NULL DEFAULT 'adult'
     );
 
 INSERT INTO
-    ticket_defs (title, price, start, expiry)
+    ticket_defs (title, price, start, expiry, mode, passenger)
 VALUES
     (
         'Term 2 Bee Bus Student',
         10500,
         '2025-01-01T04:00:00.000000000',
-        '2025-04-01T03:59:00.000000000'
+        '2025-04-01T03:59:00.000000000',
+        'bus',
+        'student'
     ),
     (
         'Term 3 Bee Bus Student',
         10500,
         '2025-04-01T04:00:00.000000000',
-        '2025-06-30T03:59:00.000000000'
+        '2025-06-30T03:59:00.000000000',
+        'bus',
+        'student'
     );
\ No newline at end of file
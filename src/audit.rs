@@ -0,0 +1,113 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    models::{
+        self,
+        audit::{self, Action},
+        ticket::TicketId,
+        user::{User, UserId},
+    },
+    State,
+};
+
+/// Records [`audit::AuditEvent`]s for the current request, filling in who
+/// is signed in and where the request came from.
+pub struct Auditor {
+    state: State,
+    actor: Option<UserId>,
+    ip: Option<String>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Auditor {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let actor = parts
+            .extensions
+            .get::<Option<User>>()
+            .cloned()
+            .flatten()
+            .map(|user| user.id);
+        let ip = parts
+            .headers
+            .get("cf-connecting-ip")
+            .and_then(|ip| ip.to_str().ok())
+            .map(str::to_owned);
+
+        Ok(Auditor {
+            state: State::from_extensions(&parts.extensions),
+            actor,
+            ip,
+        })
+    }
+}
+
+impl Auditor {
+    /// For work done outside of a request, like a scheduled job, which
+    /// nobody in particular is doing.
+    pub fn system(state: State) -> Self {
+        Auditor {
+            state,
+            actor: None,
+            ip: None,
+        }
+    }
+
+    pub async fn record(&self, event: Event) {
+        self.state
+            .db
+            .run(audit::Insert {
+                // logins happen before anyone is signed in, so they act as themselves
+                actor: self.actor.or(event.actor),
+                action: event.action,
+                target_user: event.target_user,
+                target_ticket: event.target_ticket,
+                detail: event.detail,
+                ip: self.ip.clone(),
+                created_at: models::timestamp(models::now()),
+            })
+            .await;
+    }
+}
+
+/// What happened, built up with the methods below.
+pub struct Event {
+    action: Action,
+    actor: Option<UserId>,
+    target_user: Option<UserId>,
+    target_ticket: Option<TicketId>,
+    detail: Option<String>,
+}
+
+impl Event {
+    pub fn new(action: Action) -> Self {
+        Event {
+            action,
+            actor: None,
+            target_user: None,
+            target_ticket: None,
+            detail: None,
+        }
+    }
+
+    /// Done by `user` when nobody was signed in yet, like a login.
+    pub fn by(mut self, user: UserId) -> Self {
+        self.actor = Some(user);
+        self
+    }
+
+    pub fn user(mut self, user: UserId) -> Self {
+        self.target_user = Some(user);
+        self
+    }
+
+    pub fn ticket(mut self, ticket: TicketId) -> Self {
+        self.target_ticket = Some(ticket);
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
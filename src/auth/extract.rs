@@ -0,0 +1,64 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_htmx::{HxRedirect, HxRequest};
+
+use crate::models::user::User;
+
+/// The signed in user, as found by [`super::user_middleware`].
+///
+/// Rejects with [`LoginRequired`] when nobody is signed in.
+pub struct AuthUser(pub User);
+
+/// The signed in user, if there is one.
+pub struct MaybeUser(pub Option<User>);
+
+/// Sends the browser to the login page.
+///
+/// htmx requests get an `HX-Redirect` so the whole page navigates, instead
+/// of the login form (or nothing at all) being swapped into a fragment.
+pub struct LoginRequired {
+    htmx: bool,
+}
+
+impl IntoResponse for LoginRequired {
+    fn into_response(self) -> Response {
+        if self.htmx {
+            (
+                StatusCode::UNAUTHORIZED,
+                HxRedirect(axum::http::Uri::from_static("/auth/login")),
+                (),
+            )
+                .into_response()
+        } else {
+            Redirect::to("/auth/login").into_response()
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for MaybeUser {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user = parts.extensions.get::<Option<User>>().cloned().flatten();
+        Ok(MaybeUser(user))
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = LoginRequired;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(MaybeUser(user)) = MaybeUser::from_request_parts(parts, state).await;
+
+        match user {
+            Some(user) => Ok(AuthUser(user)),
+            None => {
+                let Ok(HxRequest(htmx)) = HxRequest::from_request_parts(parts, state).await;
+                Err(LoginRequired { htmx })
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Just enough JWT handling to validate OpenID Connect ID tokens.
+//!
+//! Only `RS256` signatures are accepted, which every mainstream provider
+//! supports and is the default for ID tokens.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rsa::{
+    pkcs1v15::{Signature, VerifyingKey},
+    signature::Verifier,
+    BigUint, RsaPublicKey,
+};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// Leeway given to `exp` and `iat` for clock drift between us and the issuer.
+const LEEWAY_SECONDS: i64 = 60;
+
+#[derive(Debug)]
+pub enum JwtError {
+    Malformed,
+    UnsupportedAlgorithm(String),
+    UnknownKey,
+    BadSignature,
+    WrongIssuer,
+    WrongAudience,
+    Expired,
+    IssuedInFuture,
+    NonceMismatch,
+}
+
+impl std::fmt::Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwtError::Malformed => f.write_str("token is malformed"),
+            JwtError::UnsupportedAlgorithm(alg) => write!(f, "unsupported algorithm {alg}"),
+            JwtError::UnknownKey => f.write_str("no matching key in JWKS"),
+            JwtError::BadSignature => f.write_str("signature does not verify"),
+            JwtError::WrongIssuer => f.write_str("issuer does not match"),
+            JwtError::WrongAudience => f.write_str("audience does not match"),
+            JwtError::Expired => f.write_str("token has expired"),
+            JwtError::IssuedInFuture => f.write_str("token was issued in the future"),
+            JwtError::NonceMismatch => f.write_str("nonce does not match"),
+        }
+    }
+}
+
+/// A JSON Web Key Set, as served from a provider's `jwks_uri`.
+#[derive(Deserialize)]
+pub struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    usage: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+impl Jwk {
+    fn rsa_key(&self) -> Option<RsaPublicKey> {
+        if self.kty != "RSA" || self.usage.as_deref().is_some_and(|u| u != "sig") {
+            return None;
+        }
+
+        let n = URL_SAFE_NO_PAD.decode(self.n.as_ref()?).ok()?;
+        let e = URL_SAFE_NO_PAD.decode(self.e.as_ref()?).ok()?;
+
+        RsaPublicKey::new(BigUint::from_bytes_be(&n), BigUint::from_bytes_be(&e)).ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == client_id,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: i64,
+    iat: Option<i64>,
+    auth_time: Option<i64>,
+    nonce: Option<String>,
+    email: Option<String>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+}
+
+/// The claims of a verified ID token that we make use of.
+pub struct IdToken {
+    pub subject: String,
+    /// When the user last actually signed in at the provider, which can be
+    /// well before this token if they had a session there.
+    pub auth_time: Option<i64>,
+    pub email: Option<String>,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
+
+/// What a valid ID token must match.
+pub struct Expected<'a> {
+    pub issuer: &'a str,
+    pub client_id: &'a str,
+    pub nonce: &'a str,
+    /// Current unix time in seconds.
+    pub now: i64,
+}
+
+pub fn verify_id_token(token: &str, jwks: &Jwks, expected: Expected) -> Result<IdToken, JwtError> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(JwtError::Malformed);
+    };
+
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| JwtError::Malformed)
+    };
+
+    let header: Header =
+        serde_json::from_slice(&decode(header)?).map_err(|_| JwtError::Malformed)?;
+    if header.alg != "RS256" {
+        return Err(JwtError::UnsupportedAlgorithm(header.alg));
+    }
+
+    // a provider mid key-rotation publishes several keys; without a `kid` try them all
+    let candidates = jwks
+        .keys
+        .iter()
+        .filter(|key| header.kid.is_none() || key.kid == header.kid)
+        .filter_map(Jwk::rsa_key)
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Err(JwtError::UnknownKey);
+    }
+
+    let signature =
+        Signature::try_from(decode(signature)?.as_slice()).map_err(|_| JwtError::Malformed)?;
+    let signed = &token[..header_and_payload_len(token)];
+    candidates
+        .into_iter()
+        .map(VerifyingKey::<Sha256>::new)
+        .find(|key| key.verify(signed.as_bytes(), &signature).is_ok())
+        .ok_or(JwtError::BadSignature)?;
+
+    let claims: Claims =
+        serde_json::from_slice(&decode(payload)?).map_err(|_| JwtError::Malformed)?;
+
+    if claims.iss != expected.issuer {
+        return Err(JwtError::WrongIssuer);
+    }
+    if !claims.aud.contains(expected.client_id) {
+        return Err(JwtError::WrongAudience);
+    }
+    if claims.exp + LEEWAY_SECONDS < expected.now {
+        return Err(JwtError::Expired);
+    }
+    if claims
+        .iat
+        .is_some_and(|iat| iat - LEEWAY_SECONDS > expected.now)
+    {
+        return Err(JwtError::IssuedInFuture);
+    }
+    if claims.nonce.as_deref() != Some(expected.nonce) {
+        return Err(JwtError::NonceMismatch);
+    }
+
+    Ok(IdToken {
+        subject: claims.sub,
+        auth_time: claims.auth_time,
+        email: claims.email,
+        preferred_username: claims.preferred_username,
+        name: claims.name,
+    })
+}
+
+/// Length of the `header.payload` prefix that the signature covers.
+fn header_and_payload_len(token: &str) -> usize {
+    token.rfind('.').unwrap_or(token.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use rand_chacha::{rand_core::SeedableRng, ChaCha8Rng};
+    use rsa::{
+        pkcs1v15::SigningKey,
+        signature::{SignatureEncoding, Signer},
+        traits::PublicKeyParts,
+        RsaPrivateKey,
+    };
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const ISSUER: &str = "https://issuer.example";
+    const CLIENT_ID: &str = "bee";
+    const NONCE: &str = "n-0S6_WzA2Mj";
+    const NOW: i64 = 1_750_000_000;
+
+    /// A mock issuer's signing key. Generating one is slow, so it's shared.
+    fn issuer_key() -> &'static RsaPrivateKey {
+        static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+        KEY.get_or_init(|| key(1))
+    }
+
+    fn key(seed: u64) -> RsaPrivateKey {
+        RsaPrivateKey::new(&mut ChaCha8Rng::seed_from_u64(seed), 1024).unwrap()
+    }
+
+    fn jwks(key: &RsaPrivateKey) -> Jwks {
+        serde_json::from_value(json!({
+            "keys": [{
+                "kty": "RSA",
+                "kid": "key-1",
+                "use": "sig",
+                "n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                "e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn claims() -> Value {
+        json!({
+            "iss": ISSUER,
+            "sub": "248289761001",
+            "aud": CLIENT_ID,
+            "exp": NOW + 300,
+            "iat": NOW,
+            "auth_time": NOW - 5,
+            "nonce": NONCE,
+            "email": "rider@example.com",
+            "preferred_username": "rider",
+        })
+    }
+
+    fn sign(key: &RsaPrivateKey, claims: &Value) -> String {
+        let encode = |value: Value| URL_SAFE_NO_PAD.encode(value.to_string());
+        let signed = format!(
+            "{}.{}",
+            encode(json!({ "alg": "RS256", "kid": "key-1" })),
+            encode(claims.clone())
+        );
+        let signature = SigningKey::<Sha256>::new(key.clone()).sign(signed.as_bytes());
+
+        format!("{signed}.{}", URL_SAFE_NO_PAD.encode(signature.to_vec()))
+    }
+
+    fn expected() -> Expected<'static> {
+        Expected {
+            issuer: ISSUER,
+            client_id: CLIENT_ID,
+            nonce: NONCE,
+            now: NOW,
+        }
+    }
+
+    /// Verifies `claims` signed by the mock issuer.
+    fn verify(claims: &Value) -> Result<IdToken, JwtError> {
+        verify_id_token(&sign(issuer_key(), claims), &jwks(issuer_key()), expected())
+    }
+
+    #[test]
+    fn accepts_a_valid_token() {
+        let id_token = verify(&claims()).unwrap();
+
+        assert_eq!(id_token.subject, "248289761001");
+        assert_eq!(id_token.auth_time, Some(NOW - 5));
+        assert_eq!(id_token.email.as_deref(), Some("rider@example.com"));
+        assert_eq!(id_token.preferred_username.as_deref(), Some("rider"));
+    }
+
+    #[test]
+    fn accepts_one_of_several_audiences() {
+        let mut claims = claims();
+        claims["aud"] = json!(["other", CLIENT_ID]);
+
+        assert!(verify(&claims).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_bad_signature() {
+        let token = sign(&key(2), &claims());
+        let result = verify_id_token(&token, &jwks(issuer_key()), expected());
+
+        assert!(matches!(result, Err(JwtError::BadSignature)));
+    }
+
+    #[test]
+    fn rejects_tampered_claims() {
+        let token = sign(issuer_key(), &claims());
+        let (signed, signature) = token.rsplit_once('.').unwrap();
+        let (header, _) = signed.split_once('.').unwrap();
+        let mut claims = claims();
+        claims["sub"] = json!("someone-else");
+        let tampered = format!(
+            "{header}.{}.{signature}",
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+
+        let result = verify_id_token(&tampered, &jwks(issuer_key()), expected());
+        assert!(matches!(result, Err(JwtError::BadSignature)));
+    }
+
+    #[test]
+    fn rejects_the_wrong_issuer() {
+        let mut claims = claims();
+        claims["iss"] = json!("https://evil.example");
+
+        assert!(matches!(verify(&claims), Err(JwtError::WrongIssuer)));
+    }
+
+    #[test]
+    fn rejects_the_wrong_audience() {
+        let mut claims = claims();
+        claims["aud"] = json!(["other", "another"]);
+
+        assert!(matches!(verify(&claims), Err(JwtError::WrongAudience)));
+    }
+
+    #[test]
+    fn rejects_an_expired_token() {
+        let mut claims = claims();
+        claims["exp"] = json!(NOW - LEEWAY_SECONDS - 1);
+
+        assert!(matches!(verify(&claims), Err(JwtError::Expired)));
+
+        // within the leeway for clock drift
+        claims["exp"] = json!(NOW - LEEWAY_SECONDS + 1);
+        assert!(verify(&claims).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_nonce_mismatch() {
+        let mut claims = claims();
+        claims["nonce"] = json!("replayed");
+
+        assert!(matches!(verify(&claims), Err(JwtError::NonceMismatch)));
+
+        claims.as_object_mut().unwrap().remove("nonce");
+        assert!(matches!(verify(&claims), Err(JwtError::NonceMismatch)));
+    }
+
+    #[test]
+    fn rejects_other_algorithms() {
+        let token = sign(issuer_key(), &claims());
+        let (_, rest) = token.split_once('.').unwrap();
+        let none = format!(
+            "{}.{rest}",
+            URL_SAFE_NO_PAD.encode(json!({ "alg": "none" }).to_string())
+        );
+
+        let result = verify_id_token(&none, &jwks(issuer_key()), expected());
+        assert!(matches!(result, Err(JwtError::UnsupportedAlgorithm(alg)) if alg == "none"));
+    }
+}
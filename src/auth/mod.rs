@@ -0,0 +1,436 @@
+mod extract;
+mod jwt;
+pub(crate) mod oidc;
+pub mod role;
+pub(crate) mod token;
+
+pub use extract::{AuthUser, MaybeUser};
+pub use role::RequireRole;
+
+use argon2::{password_hash::SaltString, Argon2};
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension,
+};
+use axum::{Form, Router};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use axum_htmx::{HxRequest, HxReswap, HxRetarget, SwapOption};
+use maud::{html, Markup};
+use rand_chacha::rand_core::SeedableRng;
+use serde::Deserialize;
+
+use crate::{
+    audit::{Auditor, Event},
+    config::Registration,
+    error::{AppError, ErrorContext},
+    models::{
+        self,
+        audit::Action,
+        invite,
+        user::{self, Role, User},
+    },
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/register", get(register_form).post(register))
+        .route("/login", get(login_form).post(login))
+        .route("/password", post(change_password))
+        .route("/logout", get(logout))
+        .nest("/oidc", oidc::router())
+}
+
+/// A random, unguessable token for session ids and login state.
+pub(crate) fn random_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Stores the user in a new session and hands its id to the browser.
+async fn start_session(jar: CookieJar, state: &State, user: User) -> CookieJar {
+    let session_id = format!("{}:{}", user.id, random_token());
+
+    state
+        .db
+        .run(user::RecordLogin {
+            id: user.id,
+            now: models::timestamp(models::now()),
+        })
+        .await;
+
+    state.sessions.put(session_id.clone(), user).await;
+
+    let mut cookie = Cookie::new("session", session_id);
+    cookie.set_path("/");
+
+    jar.add(cookie)
+}
+
+async fn login_form(HxRequest(htmx): HxRequest, Extension(state): Extension<State>) -> Markup {
+    let form = html! {
+        div style="width: 80%; max-width: 600px; margin: auto; padding: 4em;" {
+            form hx-post="/auth/login" hx-target="body" {
+                label for="username" {"Username: "}
+                input name="username" type="text";
+
+                label for="password" {"Password: "}
+                input name="password" type="text";
+
+                input type="submit" value="Login";
+            }
+            @for provider in &state.config.oidc_providers {
+                a .button href={ "/auth/oidc/" (provider.name) } {
+                    "Sign in with " (provider.label)
+                }
+            }
+        }
+    };
+
+    // a redirect from a page that needed a login lands here without htmx
+    if htmx {
+        form
+    } else {
+        crate::markup::page(None, form)
+    }
+}
+
+async fn register_form(Extension(state): Extension<State>) -> Markup {
+    registration(state.config.registration, None)
+}
+
+fn registration(registration: Registration, error: Option<&str>) -> Markup {
+    html! {
+        #register style="width: 80%; max-width: 600px; margin: auto; padding: 4em;" {
+            @if registration == Registration::Closed {
+                p { "Registration is closed." }
+            } @else {
+                form hx-post="/auth/register" hx-target="body" {
+                    @if let Some(error) = error {
+                        p .error { (error) }
+                    }
+
+                    label for="username" {"Username: "}
+                    input name="username" type="text";
+
+                    label for="password" {"Password: "}
+                    input name="password" type="text";
+
+                    @if registration == Registration::InviteOnly {
+                        label for="invite" {"Invite code: "}
+                        input name="invite" type="text";
+                    }
+
+                    input type="submit" value="Register";
+                }
+            }
+        }
+    }
+}
+
+pub async fn user_middleware(
+    jar: CookieJar,
+    Extension(state): Extension<State>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    // scripts send a token instead of a session cookie
+    let (parts, body) = request.into_parts();
+    let authenticated = token::authenticate(&state, &parts).await;
+    request = Request::from_parts(parts, body);
+    match authenticated {
+        Ok(Some(user)) => {
+            request.extensions_mut().insert(Some(user));
+            return next.run(request).await;
+        }
+        Ok(None) => {}
+        Err(error) => return ErrorContext::new(&request).respond(error),
+    }
+
+    if let Some(cookie) = jar.get("session") {
+        let session_id = cookie.value().to_owned();
+        if let Some(session) = state.sessions.get(session_id.clone()).await {
+            // the session holds a copy, so check the account is still usable
+            match state.db.query_one(user::GetById { id: session.id }).await {
+                Some(user) if !user.is_disabled() => {
+                    request.extensions_mut().insert(Some(user));
+                    return next.run(request).await;
+                }
+                _ => state.sessions.remove(session_id).await,
+            }
+        };
+    }
+
+    request.extensions_mut().insert(None::<User>);
+    next.run(request).await
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+/// Checks a password against the user's hash. Accounts created through an
+/// identity provider have no password, so never match.
+pub(crate) fn verify_password(user: &User, password: &str) -> bool {
+    use argon2::PasswordHash;
+
+    PasswordHash::new(&user.password_hash).is_ok_and(|hash| {
+        hash.verify_password(&[&Argon2::default()], password)
+            .is_ok()
+    })
+}
+
+pub(crate) const DISABLED: &str = "This account has been disabled.";
+
+/// Hashes a new password for storing.
+fn hash_password(password: &str) -> Result<String, AppError> {
+    use argon2::PasswordHasher;
+
+    let salt = SaltString::generate(&mut rand_chacha::ChaCha12Rng::from_seed(Default::default()));
+
+    let argon = Argon2::default();
+    let password_hash = argon
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(AppError::internal)?;
+
+    Ok(password_hash.serialize().to_string())
+}
+
+pub async fn login(
+    jar: CookieJar,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(payload): Form<LoginRequest>,
+) -> Result<Response, AppError> {
+    let incorrect = || AppError::Unauthorized(String::from("Incorrect username or password."));
+    let failed = |reason: &str| {
+        Event::new(Action::LoginFailed).detail(format!("{}: {reason}", payload.username))
+    };
+
+    let Some(user) = state
+        .db
+        .query_one(user::Get {
+            username: payload.username.clone(),
+        })
+        .await
+    else {
+        auditor.record(failed("no such user")).await;
+        return Err(incorrect());
+    };
+
+    if !verify_password(&user, &payload.password) {
+        auditor.record(failed("wrong password").user(user.id)).await;
+        return Err(incorrect());
+    }
+    if user.is_disabled() {
+        auditor.record(failed("disabled").user(user.id)).await;
+        return Err(AppError::Unauthorized(String::from(DISABLED)));
+    }
+    if user.password_change_required_at.is_some() {
+        return Ok(password_form(&user.username, None).into_response());
+    }
+
+    auditor
+        .record(Event::new(Action::Login).by(user.id).user(user.id))
+        .await;
+    let jar = start_session(jar, &state, user.clone()).await;
+
+    Ok((jar, crate::markup::root(Some(user))).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    username: String,
+    password: String,
+    #[serde(default)]
+    invite: String,
+}
+
+pub async fn register(
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(payload): Form<RegisterRequest>,
+) -> Result<Response, AppError> {
+    let mode = state.config.registration;
+    // put the form back in place with the reason, rather than replacing the page
+    let rejected = |error: &str| {
+        (
+            HxRetarget(String::from("#register")),
+            HxReswap(SwapOption::OuterHtml),
+            registration(mode, Some(error)),
+        )
+            .into_response()
+    };
+
+    if mode == Registration::Closed {
+        return Ok(rejected("Registration is closed."));
+    }
+
+    // taking the username first means a clash doesn't use up an invite
+    let Some(user) = state
+        .db
+        .query_one(user::Insert {
+            username: payload.username,
+            password: hash_password(&payload.password)?,
+        })
+        .await
+    else {
+        return Ok((
+            StatusCode::CONFLICT,
+            rejected("That username is already taken."),
+        )
+            .into_response());
+    };
+
+    let invite = match mode {
+        Registration::InviteOnly => {
+            let Some(invite) = state
+                .db
+                .query_one(invite::Redeem {
+                    code: payload.invite.trim().to_uppercase(),
+                    now: models::timestamp(models::now()),
+                })
+                .await
+            else {
+                // give the username back
+                state.db.run(user::Delete { id: user.id }).await;
+                return Ok(rejected("That invite code is invalid or has been used up."));
+            };
+            Some(invite)
+        }
+        _ => None,
+    };
+
+    state
+        .db
+        .run(user::GrantRole {
+            user: user.id,
+            role: Role::Rider,
+        })
+        .await;
+
+    let mut event = Event::new(Action::Register).by(user.id).user(user.id);
+    if let Some(invite) = &invite {
+        event = event.detail(format!("invite {}", invite.code));
+    }
+    auditor.record(event).await;
+
+    if let Some(invite) = invite {
+        state
+            .db
+            .run(invite::RecordUse {
+                invite: invite.id,
+                user: user.id,
+                used_at: models::timestamp(models::now()),
+            })
+            .await;
+    }
+
+    Ok(crate::markup::root(None).into_response())
+}
+
+/// Asks for a new password after an admin required a change. The current
+/// password is asked for again, as no session has been started yet.
+fn password_form(username: &str, error: Option<&str>) -> Markup {
+    html! {
+        #change-password style="width: 80%; max-width: 600px; margin: auto; padding: 4em;" {
+            form hx-post="/auth/password" hx-target="body" {
+                p { "Your password has to be changed before you can sign in." }
+
+                @if let Some(error) = error {
+                    p .error { (error) }
+                }
+
+                input name="username" type="hidden" value=(username);
+
+                label for="password" {"Current password: "}
+                input name="password" type="password";
+
+                label for="new_password" {"New password: "}
+                input name="new_password" type="password";
+
+                input type="submit" value="Change password";
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    username: String,
+    password: String,
+    new_password: String,
+}
+
+pub async fn change_password(
+    jar: CookieJar,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(payload): Form<ChangePasswordRequest>,
+) -> Result<Response, AppError> {
+    let rejected = |error: &str| {
+        (
+            HxRetarget(String::from("#change-password")),
+            HxReswap(SwapOption::OuterHtml),
+            password_form(&payload.username, Some(error)),
+        )
+            .into_response()
+    };
+
+    let Some(user) = state
+        .db
+        .query_one(user::Get {
+            username: payload.username.clone(),
+        })
+        .await
+        .filter(|user| verify_password(user, &payload.password))
+    else {
+        return Ok(rejected("Incorrect username or password."));
+    };
+    if user.is_disabled() {
+        return Err(AppError::Unauthorized(String::from(DISABLED)));
+    }
+    if payload.new_password.is_empty() || payload.new_password == payload.password {
+        return Ok(rejected("Choose a new password."));
+    }
+
+    state
+        .db
+        .run(user::ChangePassword {
+            id: user.id,
+            password_hash: hash_password(&payload.new_password)?,
+        })
+        .await;
+
+    let user = User {
+        password_change_required_at: None,
+        ..user
+    };
+    auditor
+        .record(
+            Event::new(Action::Login)
+                .by(user.id)
+                .user(user.id)
+                .detail("after password change"),
+        )
+        .await;
+    let jar = start_session(jar, &state, user.clone()).await;
+
+    Ok((jar, crate::markup::root(Some(user))).into_response())
+}
+
+pub async fn logout(jar: CookieJar, Extension(state): Extension<State>) -> impl IntoResponse {
+    if let Some(cookie) = jar.get("session") {
+        state.sessions.remove(cookie.value().to_owned()).await;
+    }
+
+    let mut cookie = Cookie::from("session");
+    cookie.set_path("/");
+
+    (jar.remove(cookie), crate::markup::root(None))
+}
@@ -0,0 +1,499 @@
+//! OpenID Connect sign in, using the authorization code flow with PKCE.
+//!
+//! `/auth/oidc/{provider}` sends the browser to the provider and
+//! `/auth/oidc/{provider}/callback` finishes the login once it comes back.
+//! The state, nonce and PKCE verifier for a login in progress live in a short
+//! lived cookie scoped to these routes.
+//!
+//! The same flow confirms deleting a password-less account, through
+//! [`confirm_deletion`]: the provider is made to ask for the user's
+//! credentials again, so a stolen session alone can't erase the account.
+
+use axum::{
+    extract::{Path, Query},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Extension, Router,
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use worker::Url;
+
+use super::{
+    jwt::{self, IdToken, Jwks},
+    MaybeUser,
+};
+use crate::{
+    audit::{Auditor, Event},
+    config::{OidcProvider, Registration},
+    error::AppError,
+    fetch,
+    models::{
+        audit::Action,
+        user::{self, Role, User},
+    },
+    routes::account::data::erase_account,
+    State,
+};
+
+const PENDING_COOKIE: &str = "oidc";
+/// Leeway given to `auth_time` for clock drift between us and the provider.
+const AUTH_TIME_LEEWAY_SECONDS: i64 = 60;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/{provider}", get(begin))
+        .route("/{provider}/callback", get(callback))
+}
+
+/// Why the browser was sent to the provider.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Purpose {
+    SignIn,
+    DeleteAccount,
+}
+
+/// A login that has been sent to the provider and not yet returned.
+#[derive(Serialize, Deserialize)]
+struct Pending {
+    provider: String,
+    purpose: Purpose,
+    /// Unix time the login started, which a confirming sign in must follow.
+    started_at: i64,
+    state: String,
+    nonce: String,
+    verifier: String,
+}
+
+impl Pending {
+    fn to_cookie(&self) -> Cookie<'static> {
+        let value = URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap());
+
+        Cookie::build((PENDING_COOKIE, value))
+            .path("/auth/oidc")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::minutes(10))
+            .build()
+    }
+
+    fn from_cookie(cookie: &Cookie) -> Option<Self> {
+        let json = URL_SAFE_NO_PAD.decode(cookie.value()).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct Endpoints {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+/// Uses the configured endpoints, falling back to the issuer's discovery document.
+async fn endpoints(state: &State, provider: &OidcProvider) -> Option<Endpoints> {
+    if let (Some(authorization_endpoint), Some(token_endpoint), Some(jwks_uri)) = (
+        &provider.authorization_endpoint,
+        &provider.token_endpoint,
+        &provider.jwks_uri,
+    ) {
+        return Some(Endpoints {
+            authorization_endpoint: authorization_endpoint.clone(),
+            token_endpoint: token_endpoint.clone(),
+            jwks_uri: jwks_uri.clone(),
+        });
+    }
+
+    let discovery = format!(
+        "{}/.well-known/openid-configuration",
+        provider.issuer.trim_end_matches('/')
+    );
+    let discovered: Endpoints = state
+        .fetch
+        .send(fetch::Request::get(discovery))
+        .await
+        .filter(fetch::Response::is_success)?
+        .json()?;
+
+    Some(Endpoints {
+        authorization_endpoint: provider
+            .authorization_endpoint
+            .clone()
+            .unwrap_or(discovered.authorization_endpoint),
+        token_endpoint: provider
+            .token_endpoint
+            .clone()
+            .unwrap_or(discovered.token_endpoint),
+        jwks_uri: provider.jwks_uri.clone().unwrap_or(discovered.jwks_uri),
+    })
+}
+
+fn redirect_uri(state: &State, provider: &OidcProvider) -> String {
+    format!(
+        "{}/auth/oidc/{}/callback",
+        state.config.origin, provider.name
+    )
+}
+
+async fn begin(
+    Path(provider): Path<String>,
+    jar: CookieJar,
+    Extension(state): Extension<State>,
+) -> Result<(CookieJar, Redirect), AppError> {
+    let (jar, url) = authorize(&state, &provider, jar, Purpose::SignIn).await?;
+
+    Ok((jar, Redirect::to(url.as_str())))
+}
+
+/// Starts a sign in with `provider` that deletes the signed in user's
+/// account once it comes back, returning where to send the browser.
+pub(crate) async fn confirm_deletion(
+    state: &State,
+    provider: &str,
+    jar: CookieJar,
+) -> Result<(CookieJar, Url), AppError> {
+    authorize(state, provider, jar, Purpose::DeleteAccount).await
+}
+
+async fn authorize(
+    state: &State,
+    provider: &str,
+    jar: CookieJar,
+    purpose: Purpose,
+) -> Result<(CookieJar, Url), AppError> {
+    let Some(provider) = state.config.oidc_provider(provider) else {
+        return Err(AppError::NotFound);
+    };
+    let Some(endpoints) = endpoints(state, provider).await else {
+        return Err(AppError::Upstream(format!(
+            "could not resolve endpoints for {}",
+            provider.name
+        )));
+    };
+
+    let pending = Pending {
+        provider: provider.name.clone(),
+        purpose,
+        started_at: time::UtcDateTime::now().unix_timestamp(),
+        state: super::random_token(),
+        nonce: super::random_token(),
+        verifier: format!("{}{}", super::random_token(), super::random_token()),
+    };
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(pending.verifier.as_bytes()));
+
+    let redirect_uri = redirect_uri(state, provider);
+    let mut params = vec![
+        ("response_type", "code"),
+        ("client_id", &provider.client_id),
+        ("redirect_uri", &redirect_uri),
+        ("scope", &provider.scopes),
+        ("state", &pending.state),
+        ("nonce", &pending.nonce),
+        ("code_challenge", &challenge),
+        ("code_challenge_method", "S256"),
+    ];
+    if purpose == Purpose::DeleteAccount {
+        // make the provider ask for credentials even if it has a session
+        params.extend([("prompt", "login"), ("max_age", "0")]);
+    }
+
+    let Ok(url) = Url::parse_with_params(&endpoints.authorization_endpoint, params) else {
+        return Err(AppError::Upstream(format!(
+            "{} has an invalid authorization endpoint",
+            provider.name
+        )));
+    };
+
+    Ok((jar.add(pending.to_cookie()), url))
+}
+
+#[derive(Deserialize)]
+struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+async fn callback(
+    Path(provider): Path<String>,
+    Query(params): Query<CallbackParams>,
+    jar: CookieJar,
+    MaybeUser(user): MaybeUser,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Response, AppError> {
+    let Some(provider) = state.config.oidc_provider(&provider) else {
+        return Err(AppError::NotFound);
+    };
+
+    let pending = jar.get(PENDING_COOKIE).and_then(Pending::from_cookie);
+    let jar = jar.remove(Cookie::build(PENDING_COOKIE).path("/auth/oidc"));
+
+    if let Some(error) = params.error {
+        tracing::info!("{} returned error: {error}", provider.name);
+        return Ok((jar, Redirect::to("/")).into_response());
+    }
+
+    let Some(pending) =
+        pending.filter(|p| p.provider == provider.name && params.state.as_ref() == Some(&p.state))
+    else {
+        return Err(AppError::BadRequest(String::from(
+            "This sign in has expired, please try again.",
+        )));
+    };
+    let Some(code) = params.code else {
+        return Err(AppError::BadRequest(String::from(
+            "The sign in didn't include an authorization code.",
+        )));
+    };
+
+    let id_token = verified_id_token(&state, provider, &pending, &code).await?;
+
+    if pending.purpose == Purpose::DeleteAccount {
+        return delete_account(jar, &state, provider, &pending, id_token, user).await;
+    }
+
+    let user = match state
+        .db
+        .query_one(user::GetByIdentity {
+            provider: provider.name.clone(),
+            subject: id_token.subject.clone(),
+        })
+        .await
+    {
+        Some(linked) => linked,
+        None => {
+            // link to whoever is signed in, otherwise this is a new account
+            let user = match user {
+                Some(user) => user,
+                None if state.config.registration == Registration::Open => {
+                    let user = create_user(&state, &id_token).await?;
+                    auditor
+                        .record(
+                            Event::new(Action::Register)
+                                .by(user.id)
+                                .user(user.id)
+                                .detail(provider.name.as_str()),
+                        )
+                        .await;
+                    user
+                }
+                None => {
+                    // invite codes can only be given through the register form
+                    return Err(AppError::Unauthorized(format!(
+                        "No account is linked to this {} sign in. Register first, then sign in with {} to link it.",
+                        provider.label, provider.label
+                    )));
+                }
+            };
+            state
+                .db
+                .run(user::LinkIdentity {
+                    user: user.id,
+                    provider: provider.name.clone(),
+                    subject: id_token.subject.clone(),
+                })
+                .await;
+            // a concurrent first sign in may have linked it first, and
+            // whoever did is who this identity signs in as
+            state
+                .db
+                .query_one(user::GetByIdentity {
+                    provider: provider.name.clone(),
+                    subject: id_token.subject,
+                })
+                .await
+                .ok_or_else(|| AppError::internal("linked identity was not found"))?
+        }
+    };
+
+    if user.is_disabled() {
+        auditor
+            .record(
+                Event::new(Action::LoginFailed)
+                    .user(user.id)
+                    .detail(format!("{}: disabled", provider.name)),
+            )
+            .await;
+        return Err(AppError::Unauthorized(String::from(super::DISABLED)));
+    }
+
+    auditor
+        .record(
+            Event::new(Action::Login)
+                .by(user.id)
+                .user(user.id)
+                .detail(provider.name.as_str()),
+        )
+        .await;
+
+    let jar = super::start_session(jar, &state, user).await;
+
+    Ok((jar, Redirect::to("/")).into_response())
+}
+
+/// Finishes a [`confirm_deletion`], once the provider has vouched for the
+/// signed in user with a fresh sign in.
+async fn delete_account(
+    jar: CookieJar,
+    state: &State,
+    provider: &OidcProvider,
+    pending: &Pending,
+    id_token: IdToken,
+    user: Option<User>,
+) -> Result<Response, AppError> {
+    let Some(user) = user else {
+        return Err(AppError::Unauthorized(String::from(
+            "Sign in before deleting your account.",
+        )));
+    };
+
+    // without `auth_time` the provider may have skipped asking for credentials
+    let fresh = id_token
+        .auth_time
+        .is_some_and(|auth_time| auth_time + AUTH_TIME_LEEWAY_SECONDS >= pending.started_at);
+    if !fresh {
+        return Err(AppError::Unauthorized(format!(
+            "{} didn't ask you to sign in again, so your account wasn't deleted.",
+            provider.label
+        )));
+    }
+
+    let linked = state
+        .db
+        .query_one(user::GetByIdentity {
+            provider: provider.name.clone(),
+            subject: id_token.subject,
+        })
+        .await;
+    if linked.is_none_or(|linked| linked.id != user.id) {
+        return Err(AppError::Forbidden);
+    }
+
+    erase_account(state, &user).await;
+
+    let mut cookie = Cookie::from("session");
+    cookie.set_path("/");
+
+    Ok((jar.remove(cookie), Redirect::to("/")).into_response())
+}
+
+async fn verified_id_token(
+    state: &State,
+    provider: &OidcProvider,
+    pending: &Pending,
+    code: &str,
+) -> Result<IdToken, AppError> {
+    let failed = || {
+        AppError::Unauthorized(format!(
+            "Signing in with {} failed, please try again.",
+            provider.label
+        ))
+    };
+
+    let endpoints = endpoints(state, provider).await.ok_or_else(|| {
+        AppError::Upstream(format!("could not resolve endpoints for {}", provider.name))
+    })?;
+
+    let redirect_uri = redirect_uri(state, provider);
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", &redirect_uri),
+        ("client_id", &provider.client_id),
+        ("code_verifier", &pending.verifier),
+    ];
+    if let Some(secret) = &provider.client_secret {
+        form.push(("client_secret", secret));
+    }
+
+    let tokens = state
+        .fetch
+        .send(fetch::Request::post_form(&endpoints.token_endpoint, form))
+        .await
+        .ok_or_else(|| AppError::Upstream(format!("{} token request failed", provider.name)))?;
+    if !tokens.is_success() {
+        tracing::warn!(
+            "{} token endpoint returned {}",
+            provider.name,
+            tokens.status
+        );
+        return Err(failed());
+    }
+    let tokens: TokenResponse = tokens.json().ok_or_else(|| {
+        AppError::Upstream(format!("{} token response has no id_token", provider.name))
+    })?;
+
+    let jwks: Jwks = state
+        .fetch
+        .send(fetch::Request::get(&endpoints.jwks_uri))
+        .await
+        .filter(fetch::Response::is_success)
+        .and_then(|response| response.json())
+        .ok_or_else(|| AppError::Upstream(format!("could not fetch JWKS for {}", provider.name)))?;
+
+    jwt::verify_id_token(
+        &tokens.id_token,
+        &jwks,
+        jwt::Expected {
+            issuer: &provider.issuer,
+            client_id: &provider.client_id,
+            nonce: &pending.nonce,
+            now: time::UtcDateTime::now().unix_timestamp(),
+        },
+    )
+    .map_err(|e| {
+        tracing::warn!("{} ID token rejected: {e}", provider.name);
+        failed()
+    })
+}
+
+/// Creates a password-less account named after the identity's claims.
+async fn create_user(state: &State, id_token: &IdToken) -> Result<User, AppError> {
+    let base = id_token
+        .preferred_username
+        .as_deref()
+        .or(id_token.email.as_deref().and_then(|e| e.split('@').next()))
+        .or(id_token.name.as_deref())
+        .unwrap_or("rider")
+        .to_owned();
+
+    // the first of rider, rider2, rider3… that nobody has taken
+    let mut username = base.clone();
+    let mut suffix = 1;
+    let user = loop {
+        let inserted = state
+            .db
+            .query_one(user::Insert {
+                username,
+                password: String::new(),
+            })
+            .await;
+        if let Some(user) = inserted {
+            break user;
+        }
+
+        suffix += 1;
+        username = format!("{base}{suffix}");
+    };
+
+    state
+        .db
+        .run(user::GrantRole {
+            user: user.id,
+            role: Role::Rider,
+        })
+        .await;
+
+    Ok(user)
+}
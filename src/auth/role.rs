@@ -0,0 +1,77 @@
+use std::marker::PhantomData;
+
+use axum::{
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+
+use super::AuthUser;
+use crate::{
+    error::AppError,
+    models::user::{self, Role, User},
+    State,
+};
+
+/// A role that a route can demand with [`RequireRole`].
+pub trait RequiredRole: Send + Sync + 'static {
+    const ROLE: Role;
+}
+
+pub struct Admin;
+
+impl RequiredRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// Extracts the signed in user like [`AuthUser`], rejecting with
+/// [`AppError::Forbidden`] when they don't hold the role `R`.
+///
+/// Also usable as a layer on a whole router with
+/// [`axum::middleware::from_extractor`].
+pub struct RequireRole<R> {
+    pub user: User,
+    role: PhantomData<R>,
+}
+
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    R: RequiredRole,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let state = State::from_extensions(&parts.extensions);
+
+        if !has_role(&state, &user, R::ROLE).await {
+            return Err(AppError::Forbidden.into_response());
+        }
+
+        Ok(RequireRole {
+            user,
+            role: PhantomData,
+        })
+    }
+}
+
+/// Looked up on every check rather than kept in the session, so a revoked
+/// role takes effect straight away.
+pub async fn has_role(state: &State, user: &User, role: Role) -> bool {
+    // configured admins can always get in, so the first admin can grant the rest
+    if role == Role::Admin && state.config.is_admin(user) {
+        return true;
+    }
+
+    state
+        .db
+        .query_one(user::HasRole {
+            user: user.id,
+            role,
+        })
+        .await
+        .is_some()
+}
@@ -0,0 +1,87 @@
+use axum::http::{header, request::Parts, Method};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::AppError,
+    models::{
+        self,
+        api_token::{self, Scope},
+        user::{self, User},
+    },
+    State,
+};
+
+/// Paths a token can be used on. Account and admin pages stay session only,
+/// so a leaked token can't mint more tokens or grant itself roles.
+const TOKEN_PATHS: &[&str] = &["/api", "/tickets", "/qr"];
+
+/// A new token, prefixed so that it's recognisable if it leaks.
+pub(crate) fn generate() -> String {
+    format!("bee_{}{}", super::random_token(), super::random_token())
+}
+
+/// Tokens are only ever stored hashed; they're long and random enough that
+/// a plain SHA-256 is fine.
+pub(crate) fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// The user an `Authorization: Bearer` header belongs to, or `None` when
+/// the request doesn't carry one.
+///
+/// Takes just the head of the request, as the body can't be held across an
+/// await.
+pub(super) async fn authenticate(state: &State, request: &Parts) -> Result<Option<User>, AppError> {
+    let Some(authorization) = request.headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let invalid = || AppError::Unauthorized(String::from("Invalid or revoked API token."));
+
+    let token = authorization
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(invalid)?;
+
+    let api_token = state
+        .db
+        .query_one(api_token::GetByHash {
+            token_hash: hash(token.trim()),
+        })
+        .await
+        .ok_or_else(invalid)?;
+
+    let path = request.uri.path();
+    let allowed_path = TOKEN_PATHS
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{prefix}/")));
+    if !allowed_path {
+        return Err(AppError::Forbidden);
+    }
+
+    let needed = match request.method {
+        Method::GET | Method::HEAD | Method::OPTIONS => Scope::Read,
+        _ => Scope::Write,
+    };
+    if !api_token.scopes().contains(&needed) {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = state
+        .db
+        .query_one(user::GetById { id: api_token.user })
+        .await
+        .filter(|user| !user.is_disabled())
+        .ok_or_else(invalid)?;
+
+    state
+        .db
+        .run(api_token::Touch {
+            id: api_token.id,
+            now: models::timestamp(models::now()),
+        })
+        .await;
+
+    Ok(Some(user))
+}
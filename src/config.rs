@@ -0,0 +1,135 @@
+use serde::Deserialize;
+use worker::Env;
+
+use crate::models::user::{User, UserId};
+
+/// Deployment settings read from the worker's `[vars]` and secrets.
+#[derive(Clone)]
+pub struct Config {
+    /// Public origin of the app, e.g. `https://bee.example.com`.
+    pub origin: String,
+    pub registration: Registration,
+    /// Ids of users that always have the admin role, so the first admin can be
+    /// set up without touching the database. Ids rather than usernames, as
+    /// anyone could register a configured username that isn't taken yet.
+    pub admins: Vec<UserId>,
+    pub oidc_providers: Vec<OidcProvider>,
+}
+
+/// Who is allowed to create a new account.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Registration {
+    Open,
+    InviteOnly,
+    Closed,
+}
+
+impl std::str::FromStr for Registration {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Registration::Open),
+            "invite" => Ok(Registration::InviteOnly),
+            "closed" => Ok(Registration::Closed),
+            _ => Err(()),
+        }
+    }
+}
+
+/// An external OpenID Connect identity provider.
+///
+/// Endpoints left out of the configuration are resolved through the issuer's
+/// discovery document when a login starts.
+#[derive(Clone, Deserialize)]
+pub struct OidcProvider {
+    /// Used in the `/auth/oidc/{provider}` path.
+    pub name: String,
+    /// Shown on the login button.
+    pub label: String,
+    pub issuer: String,
+    pub client_id: String,
+    /// Read from the `OIDC_{NAME}_CLIENT_SECRET` secret, not from `[vars]`.
+    #[serde(skip)]
+    pub client_secret: Option<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: String,
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: Option<String>,
+    pub jwks_uri: Option<String>,
+}
+
+fn default_scopes() -> String {
+    String::from("openid profile email")
+}
+
+impl Config {
+    pub fn is_admin(&self, user: &User) -> bool {
+        self.admins.contains(&user.id)
+    }
+
+    pub fn oidc_provider(&self, name: &str) -> Option<&OidcProvider> {
+        self.oidc_providers.iter().find(|p| p.name == name)
+    }
+}
+
+/// Parses the comma separated `ADMINS` user ids, skipping anything else.
+fn admins(var: &str) -> Vec<UserId> {
+    var.split(',')
+        .map(str::trim)
+        .filter(|admin| !admin.is_empty())
+        .filter_map(|admin| match admin.parse() {
+            Ok(id) => Some(UserId(id)),
+            Err(_) => {
+                tracing::warn!("ignoring admin {admin:?}, ADMINS takes user ids");
+                None
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn config(env: &Env) -> Config {
+    let origin = env
+        .var("ORIGIN")
+        .map(|var| var.to_string())
+        .unwrap_or_else(|_| String::from("http://localhost:8787"));
+
+    let registration = env
+        .var("REGISTRATION")
+        .ok()
+        .and_then(|var| var.to_string().parse().ok())
+        .unwrap_or(Registration::InviteOnly);
+
+    let admins = env
+        .var("ADMINS")
+        .map(|var| admins(&var.to_string()))
+        .unwrap_or_default();
+
+    let mut oidc_providers = env
+        .object_var::<Vec<OidcProvider>>("OIDC_PROVIDERS")
+        .unwrap_or_default();
+    for provider in oidc_providers.iter_mut() {
+        let secret = format!("OIDC_{}_CLIENT_SECRET", provider.name.to_uppercase());
+        provider.client_secret = env.secret(&secret).ok().map(|s| s.to_string());
+    }
+
+    Config {
+        origin: origin.trim_end_matches('/').to_owned(),
+        registration,
+        admins,
+        oidc_providers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admins_are_user_ids() {
+        assert_eq!(admins(""), []);
+        assert_eq!(admins("1, 42,"), [UserId(1), UserId(42)]);
+        // usernames from before admins were configured by id
+        assert_eq!(admins("alice,7"), [UserId(7)]);
+    }
+}
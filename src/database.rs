@@ -17,6 +17,19 @@ impl From<u32> for Binding {
     }
 }
 
+impl From<u64> for Binding {
+    fn from(value: u64) -> Self {
+        // D1 numbers are doubles, exact up to 2^53
+        Binding((value as f64).into())
+    }
+}
+
+impl<T: Into<Binding>> From<Option<T>> for Binding {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Binding(JsValue::NULL))
+    }
+}
+
 pub trait Query {
     type Result: for<'de> serde::Deserialize<'de>;
 
@@ -0,0 +1,246 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_htmx::{HxReswap, HxRetarget, SwapOption, HX_REDIRECT, HX_REQUEST};
+use maud::{html, Markup};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{markup, models::user::User};
+
+/// Why a request failed.
+///
+/// Handlers return these rather than bare status codes so that the user is
+/// told what went wrong; [`error_middleware`] decides how to show it.
+#[derive(Debug, Clone)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden,
+    NotFound,
+    Conflict(String),
+    /// An identity provider or other service we rely on misbehaved.
+    Upstream(String),
+    /// Something that should never happen; the cause is logged, not shown.
+    Internal(String),
+}
+
+impl AppError {
+    pub fn internal(cause: impl std::fmt::Display) -> Self {
+        AppError::Internal(cause.to_string())
+    }
+
+    /// Recovers an error from a failed response that wasn't an [`AppError`],
+    /// such as an extractor's rejection.
+    fn from_response(status: StatusCode, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => AppError::Unauthorized(body),
+            StatusCode::FORBIDDEN => AppError::Forbidden,
+            StatusCode::NOT_FOUND => AppError::NotFound,
+            StatusCode::CONFLICT => AppError::Conflict(body),
+            status if status.is_server_error() => AppError::Internal(body),
+            _ => AppError::BadRequest(body),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden => StatusCode::FORBIDDEN,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// A short machine readable name for the JSON body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden => "forbidden",
+            AppError::NotFound => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Upstream(_) => "upstream",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    /// What the user is told.
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::BadRequest(message)
+            | AppError::Unauthorized(message)
+            | AppError::Conflict(message) => message,
+            AppError::Forbidden => "You don't have permission to do that.",
+            AppError::NotFound => "We couldn't find what you were looking for.",
+            AppError::Upstream(_) => "A service we depend on isn't responding. Try again later.",
+            AppError::Internal(_) => "Something went wrong on our end.",
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Upstream(cause) | AppError::Internal(cause) => f.write_str(cause),
+            _ => f.write_str(self.message()),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    /// Only carries the error out to [`error_middleware`], which has the
+    /// request to hand and renders the real body.
+    fn into_response(self) -> Response {
+        let mut response = (self.status(), self.message().to_owned()).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody<'e> {
+    pub error: ErrorDetail<'e>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorDetail<'e> {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: &'e str,
+    pub request_id: &'e str,
+}
+
+/// What is needed from a request to report an error back to it.
+pub struct ErrorContext {
+    request_id: String,
+    htmx: bool,
+    wants_json: bool,
+    user: Option<User>,
+    method: Method,
+    path: String,
+}
+
+impl ErrorContext {
+    pub fn new(request: &Request) -> Self {
+        let headers = request.headers();
+        let request_id = headers
+            .get("cf-ray")
+            .and_then(|ray| ray.to_str().ok())
+            .map(str::to_owned)
+            .unwrap_or_else(crate::auth::random_token);
+        // scripts using a bearer token want JSON whether or not they asked
+        let wants_json = request.uri().path().starts_with("/api/")
+            || headers.contains_key(header::AUTHORIZATION)
+            || headers
+                .get(header::ACCEPT)
+                .and_then(|accept| accept.to_str().ok())
+                .is_some_and(|accept| accept.contains("application/json"));
+
+        ErrorContext {
+            request_id,
+            htmx: headers.contains_key(HX_REQUEST),
+            wants_json,
+            user: request
+                .extensions()
+                .get::<Option<User>>()
+                .cloned()
+                .flatten(),
+            method: request.method().clone(),
+            path: request.uri().path().to_owned(),
+        }
+    }
+
+    /// Logs the error and renders it as JSON, an htmx fragment, or a whole
+    /// page, depending on what the request asked for.
+    pub fn respond(&self, error: AppError) -> Response {
+        let ErrorContext {
+            request_id,
+            method,
+            path,
+            ..
+        } = self;
+
+        match &error {
+            AppError::Upstream(_) | AppError::Internal(_) => {
+                tracing::error!(%request_id, %method, %path, "{error}");
+            }
+            _ => tracing::info!(%request_id, %method, %path, "{error}"),
+        }
+
+        let mut response = if self.wants_json {
+            let body = ErrorBody {
+                error: ErrorDetail {
+                    status: error.status().as_u16(),
+                    code: error.code(),
+                    message: error.message(),
+                    request_id,
+                },
+            };
+            (error.status(), Json(body)).into_response()
+        } else if self.htmx {
+            // htmx would otherwise swap the error into whatever asked for it
+            (
+                error.status(),
+                HxRetarget(String::from("#main-content")),
+                HxReswap(SwapOption::InnerHtml),
+                error_fragment(&error, request_id),
+            )
+                .into_response()
+        } else {
+            let page = markup::page(self.user.as_ref(), error_fragment(&error, request_id));
+            (error.status(), page).into_response()
+        };
+
+        if let Ok(request_id) = HeaderValue::from_str(request_id) {
+            response.headers_mut().insert("x-request-id", request_id);
+        }
+
+        response
+    }
+}
+
+/// Renders any [`AppError`], or other failed response, returned by a handler.
+pub async fn error_middleware(request: Request, next: Next) -> Response {
+    let context = ErrorContext::new(&request);
+
+    let response = next.run(request).await;
+
+    let error = match response.extensions().get::<AppError>() {
+        Some(error) => error.clone(),
+        None => {
+            let status = response.status();
+            let redirecting = response.headers().contains_key(header::LOCATION)
+                || response.headers().contains_key(HX_REDIRECT);
+            if !(status.is_client_error() || status.is_server_error()) || redirecting {
+                return response;
+            }
+
+            let body = axum::body::to_bytes(response.into_body(), 64 * 1024)
+                .await
+                .map(|body| String::from_utf8_lossy(&body).into_owned())
+                .unwrap_or_default();
+            AppError::from_response(status, body)
+        }
+    };
+
+    context.respond(error)
+}
+
+fn error_fragment(error: &AppError, request_id: &str) -> Markup {
+    html! {
+        .error-page {
+            h2 { (error.status().canonical_reason().unwrap_or("Error")) }
+            p { (error.message()) }
+            small .sub { "Reference: " code { (request_id) } }
+            a hx-get="/" hx-target="body" href="/" { "Back to your tickets" }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use worker::{wasm_bindgen_futures::spawn_local, Headers, Method, RequestInit};
+
+/// An outgoing HTTP request made on behalf of a handler.
+pub struct Request {
+    method: Method,
+    url: String,
+    headers: Vec<(&'static str, String)>,
+    body: Option<String>,
+}
+
+impl Request {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.into(),
+            headers: vec![("Accept", String::from("application/json"))],
+            body: None,
+        }
+    }
+
+    pub fn post_form<'a>(
+        url: impl Into<String>,
+        form: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form)
+            .finish();
+
+        Self {
+            method: Method::Post,
+            url: url.into(),
+            headers: vec![
+                ("Accept", String::from("application/json")),
+                (
+                    "Content-Type",
+                    String::from("application/x-www-form-urlencoded"),
+                ),
+            ],
+            body: Some(body),
+        }
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn json<T: for<'de> serde::Deserialize<'de>>(&self) -> Option<T> {
+        serde_json::from_str(&self.body).ok()
+    }
+}
+
+enum Task {
+    Send {
+        request: Request,
+        result: oneshot::Sender<Option<Response>>,
+    },
+    Close,
+}
+
+#[derive(Clone)]
+pub struct Fetcher(async_channel::Sender<Task>);
+
+impl Fetcher {
+    /// Sends the request, returning `None` if it could not be made at all.
+    pub async fn send(&self, request: Request) -> Option<Response> {
+        let (result, finished) = oneshot::channel();
+        self.0.send(Task::Send { request, result }).await.unwrap();
+        finished.await.unwrap()
+    }
+
+    pub async fn close(self) {
+        self.0.send(Task::Close).await.unwrap();
+    }
+}
+
+async fn send(request: Request) -> worker::Result<Response> {
+    let mut headers = Headers::new();
+    for (name, value) in request.headers {
+        headers.set(name, &value)?;
+    }
+
+    let mut init = RequestInit::new();
+    init.with_method(request.method)
+        .with_headers(headers)
+        .with_body(request.body.map(|body| body.into()));
+
+    let request = worker::Request::new_with_init(&request.url, &init)?;
+    let mut response = worker::Fetch::Request(request).send().await?;
+
+    Ok(Response {
+        status: response.status_code(),
+        body: response.text().await?,
+    })
+}
+
+pub(crate) fn fetcher() -> Fetcher {
+    let (tx, rx) = async_channel::bounded::<Task>(16);
+
+    spawn_local(async move {
+        while let Ok(task) = rx.recv().await {
+            match task {
+                Task::Close => return,
+                Task::Send { request, result } => {
+                    let url = request.url.clone();
+                    let response = send(request)
+                        .await
+                        .inspect_err(|e| tracing::warn!("request to {url} failed: {e}"))
+                        .ok();
+                    result.send(response).unwrap();
+                }
+            }
+        }
+    });
+
+    Fetcher(tx)
+}
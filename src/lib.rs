@@ -1,8 +1,13 @@
+mod audit;
 mod auth;
+mod config;
 mod database;
+mod error;
+mod fetch;
 mod markup;
 mod models;
 mod routes;
+mod scheduled;
 mod sessions;
 
 use axum::{middleware, routing::get, Extension, Router};
@@ -13,14 +18,39 @@ use tower_service::Service;
 struct State {
     pub db: DatabaseConn,
     pub sessions: sessions::Sessions,
+    pub fetch: fetch::Fetcher,
+    pub config: config::Config,
+}
+
+impl State {
+    /// Stops the actors behind the connections, once everything using them
+    /// is done.
+    async fn close(self) {
+        self.fetch.close().await;
+        self.sessions.close().await;
+        self.db.close().await;
+    }
+
+    /// For extractors, which run inside the `Extension` layer added in [`router`].
+    fn from_extensions(extensions: &axum::http::Extensions) -> State {
+        extensions
+            .get::<State>()
+            .cloned()
+            .expect("state is layered over every route")
+    }
 }
 
 fn router(state: State) -> Router {
     Router::new()
         .route("/", get(routes::index))
         .nest("/tickets", routes::ticket::router())
+        .nest("/dashboard", routes::dashboard::router())
         .nest("/qr", routes::qr::router())
         .nest("/auth", auth::router())
+        .nest("/account", routes::account::router())
+        .nest("/admin", routes::admin::router())
+        .nest("/api", routes::api::router())
+        .layer(middleware::from_fn(error::error_middleware))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth::user_middleware,
@@ -55,18 +85,31 @@ async fn fetch(
 ) -> worker::Result<axum::http::Response<axum::body::Body>> {
     console_error_panic_hook::set_once();
 
-    let db = database(env.clone());
-    let sessions = sessions::sessions(env.clone());
+    let state = state(&env);
+    let response = router(state.clone()).call(req).await;
+    state.close().await;
 
-    let state = State {
-        db: db.clone(),
-        sessions: sessions.clone(),
-    };
+    Ok(response?)
+}
 
-    let response = router(state).call(req).await?;
+#[worker::event(scheduled)]
+async fn scheduled(
+    _event: worker::ScheduledEvent,
+    env: worker::Env,
+    _ctx: worker::ScheduleContext,
+) {
+    console_error_panic_hook::set_once();
 
-    sessions.close().await;
-    db.close().await;
+    let state = state(&env);
+    scheduled::purge_deleted_tickets(&state, &audit::Auditor::system(state.clone())).await;
+    state.close().await;
+}
 
-    Ok(response)
+fn state(env: &worker::Env) -> State {
+    State {
+        db: database(env.clone()),
+        sessions: sessions::sessions(env.clone()),
+        fetch: fetch::fetcher(),
+        config: config::config(env),
+    }
 }
@@ -0,0 +1,175 @@
+use maud::{html, Markup};
+
+use super::date;
+use crate::{
+    config::OidcProvider,
+    models::{
+        api_token::{ApiToken, Scope},
+        audit::AuditEvent,
+        user::User,
+    },
+};
+
+/// `providers` are those the user can sign in with, which is how a
+/// password-less account confirms it's being deleted.
+pub fn overview(user: &User, providers: &[OidcProvider]) -> Markup {
+    html! {
+        .admin {
+            h2 { "Account" }
+            p {
+                a href="/account/tokens" { "API tokens" }
+                " let scripts and shortcuts use your tickets."
+            }
+
+            p {
+                a href="/account/activity" { "Recent activity" }
+                " shows sign ins and changes to your tickets."
+            }
+
+            h2 { "Tickets" }
+            form #preferences hx-post="/account/preferences" hx-target="closest .admin" hx-swap="outerHTML"
+                hx-trigger="change" {
+                label {
+                    input type="checkbox" name="count_on_close" checked[user.counts_on_close()];
+                    " Count a journey whenever I close a ticket, instead of tapping to validate"
+                }
+            }
+
+            h2 { "Your data" }
+            p {
+                a href="/account/export" download { "Download my data" }
+                " as JSON: your profile, tickets, QR codes and journeys."
+            }
+
+            h2 { "Delete account" }
+            (delete_form(user, providers, None))
+        }
+    }
+}
+
+pub fn delete_form(user: &User, providers: &[OidcProvider], error: Option<&str>) -> Markup {
+    html! {
+        form #delete-account
+            hx-post="/account/delete"
+            hx-target="body"
+            hx-confirm="Delete your account and all of your tickets? This can't be undone." {
+            p { "Your tickets, tokens and sign-in details are removed for good." }
+
+            @if let Some(error) = error {
+                p .error { (error) }
+            }
+
+            @if user.has_password() {
+                label for="password" { "Password: " }
+                input name="password" type="password";
+
+                input type="submit" value="Delete my account";
+            } @else if providers.is_empty() {
+                p .sub { "None of the ways you sign in can confirm this, so ask an admin to delete your account." }
+            } @else {
+                label for="username" { "Type your username to confirm: " }
+                input name="username" type="text";
+
+                @if let [provider] = providers {
+                    input name="provider" type="hidden" value=(provider.name);
+                    p .sub { "You'll be asked to sign in with " (provider.label) " again." }
+                } @else {
+                    label for="provider" { "Sign in again with: " }
+                    select name="provider" {
+                        @for provider in providers {
+                            option value=(provider.name) { (provider.label) }
+                        }
+                    }
+                }
+
+                input type="submit" value="Delete my account";
+            }
+        }
+    }
+}
+
+pub fn tokens(tokens: &[ApiToken], created: Option<&str>) -> Markup {
+    html! {
+        .admin {
+            h2 { "API tokens" }
+            p .sub {
+                "Send a token as " code { "Authorization: Bearer <token>" }
+                " to use the JSON API under " code { "/api/v1" } " from scripts."
+            }
+
+            @if let Some(created) = created {
+                .new-token {
+                    p { "Copy your new token now, it won't be shown again." }
+                    code { (created) }
+                }
+            }
+
+            form hx-post="/account/tokens" hx-target="#main-content" {
+                label for="name" { "Name: " }
+                input name="name" type="text" placeholder="Phone shortcut";
+
+                @for scope in Scope::ALL {
+                    label {
+                        input name=(scope.as_str()) type="checkbox" checked[scope == Scope::Read];
+                        " " code { (scope) } " " (scope.description())
+                    }
+                }
+
+                input type="submit" value="Create token";
+            }
+
+            table {
+                thead {
+                    tr {
+                        th { "Name" }
+                        th { "Scopes" }
+                        th { "Created" }
+                        th { "Last used" }
+                        th {}
+                    }
+                }
+                tbody {
+                    @for token in tokens {
+                        tr {
+                            td { (token.name) }
+                            td {
+                                @for scope in token.scopes() {
+                                    code { (scope) } " "
+                                }
+                            }
+                            td { (date(&token.created_at)) }
+                            td { (date(token.last_used_at.as_deref().unwrap_or("Never"))) }
+                            td {
+                                button
+                                    hx-delete={ "/account/tokens/" (token.id) }
+                                    hx-target="#main-content"
+                                    hx-confirm="Revoke this token? Anything using it will stop working." {
+                                    "Revoke"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn activity(user: &User, events: &[AuditEvent]) -> Markup {
+    // anyone else acting on the account is an admin
+    let name = |id| {
+        if id == user.id {
+            String::from("You")
+        } else {
+            String::from("An admin")
+        }
+    };
+
+    html! {
+        .admin {
+            h2 { "Recent activity" }
+            p .sub { "Anything you don't recognise? Change your password and revoke your API tokens." }
+            (super::audit::events(events, &name))
+        }
+    }
+}
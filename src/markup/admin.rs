@@ -0,0 +1,354 @@
+use maud::{html, Markup};
+
+use super::{date, minutes};
+use crate::models::{
+    audit::{Action, AuditEvent},
+    invite::{Invite, Registration},
+    money::Currency,
+    ticket::{DefHolders, Mode, Passenger, TicketDef, TicketId},
+    user::{Role, User, UserId, UserRole, UserSummary},
+};
+
+pub fn invites(invites: &[Invite], registrations: &[Registration]) -> Markup {
+    html! {
+        .admin {
+            h2 { "Invite codes" }
+            form hx-post="/admin/invites" hx-target="#main-content" {
+                label for="max_uses" { "Uses (blank for unlimited): " }
+                input name="max_uses" type="number" min="1" value="1";
+
+                label for="expires" { "Expires (optional): " }
+                input name="expires" type="date";
+
+                input type="submit" value="Create invite";
+            }
+            table {
+                thead {
+                    tr {
+                        th { "Code" }
+                        th { "Uses" }
+                        th { "Expires" }
+                        th { "Created" }
+                    }
+                }
+                tbody {
+                    @for invite in invites {
+                        tr {
+                            td { code { (invite.code) } }
+                            td {
+                                (invite.uses) " / "
+                                @match invite.max_uses {
+                                    Some(max) => (max),
+                                    None => "∞",
+                                }
+                            }
+                            td { (date(invite.expires.as_deref().unwrap_or("Never"))) }
+                            td { (date(&invite.created_at)) }
+                        }
+                    }
+                }
+            }
+
+            h2 { "Accounts" }
+            table {
+                thead {
+                    tr {
+                        th { "Username" }
+                        th { "Invite used" }
+                    }
+                }
+                tbody {
+                    @for registration in registrations {
+                        tr {
+                            td { (registration.username) }
+                            td {
+                                @match &registration.code {
+                                    Some(code) => code { (code) },
+                                    None => "None",
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn definitions(defs: &[TicketDef], holders: &[DefHolders]) -> Markup {
+    html! {
+        .admin {
+            h2 { "Ticket definitions" }
+            (definition_form(None))
+            table {
+                thead {
+                    tr {
+                        th { "Title" }
+                        th { "Price" }
+                        th { "For" }
+                        th { "Valid" }
+                        th { "Tickets" }
+                        th {}
+                    }
+                }
+                tbody {
+                    @for def in defs {
+                        @let url = format!("/admin/definitions/{}", def.id);
+                        @let tickets = holders
+                            .iter()
+                            .find(|holders| holders.def == def.id)
+                            .map_or(0, |holders| holders.tickets);
+                        tr {
+                            td {
+                                (def.title)
+                                @if def.is_retired() {
+                                    " " small .sub { "Retired" }
+                                }
+                            }
+                            td { (def.price) }
+                            td { (def.passenger.label()) ", " (def.mode.label()) }
+                            td { (date(&def.start)) " to " (date(&def.expiry)) }
+                            td {
+                                (tickets)
+                                @if let Some(takings) = def.price.checked_mul(tickets.into()) {
+                                    " " small .sub { "(" (takings) ")" }
+                                }
+                            }
+                            td {
+                                button hx-get=(url) hx-target="#main-content" { "Edit" }
+                                button hx-post={ (url) "/clone" } hx-target="#main-content" { "Clone" }
+                                @if def.is_retired() {
+                                    button hx-delete={ (url) "/retire" } hx-target="#main-content" { "Restore" }
+                                } @else {
+                                    button hx-post={ (url) "/retire" } hx-target="#main-content" { "Retire" }
+                                }
+                                @if tickets == 0 {
+                                    button hx-delete=(url) hx-target="#main-content"
+                                        hx-confirm={ "Delete " (def.title) "?" } { "Delete" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Creates a definition, or edits `def` when given one.
+pub fn definition_form(def: Option<&TicketDef>) -> Markup {
+    html! {
+        form #definition
+            hx-post=[def.is_none().then_some("/admin/definitions")]
+            hx-put=[def.map(|def| format!("/admin/definitions/{}", def.id))]
+            hx-target="#main-content" {
+            @if let Some(def) = def {
+                h3 { "Editing " (def.title) }
+            }
+
+            label for="title" { "Title: " }
+            input name="title" type="text" required value=[def.map(|def| &def.title)];
+
+            label for="price" { "Price: " }
+            input name="price" type="text" inputmode="decimal" placeholder="105.00"
+                value=[def.map(|def| def.price.plain())];
+            select name="currency" {
+                @for currency in Currency::ALL {
+                    option value=(currency.code())
+                        selected[def.is_some_and(|def| def.price.currency == currency)] {
+                        (currency.code())
+                    }
+                }
+            }
+
+            label for="mode" { "Mode: " }
+            select name="mode" {
+                @for mode in Mode::ALL {
+                    option value=(mode.as_str()) selected[def.is_some_and(|def| def.mode == mode)] {
+                        (mode.label())
+                    }
+                }
+            }
+
+            label for="passenger" { "Passenger: " }
+            select name="passenger" {
+                @for passenger in Passenger::ALL {
+                    option value=(passenger.as_str()) selected[def.is_some_and(|def| def.passenger == passenger)] {
+                        (passenger.label())
+                    }
+                }
+            }
+
+            label for="start" { "Valid from: " }
+            input name="start" type="datetime-local" required
+                value=[def.map(|def| minutes(&def.start))];
+
+            label for="expiry" { "Expires: " }
+            input name="expiry" type="datetime-local" required
+                value=[def.map(|def| minutes(&def.expiry))];
+
+            input type="submit" value=(if def.is_some() { "Save" } else { "Create definition" });
+            @if def.is_some() {
+                a href="/admin/definitions" { "Cancel" }
+            }
+        }
+    }
+}
+
+pub fn users(users: &[UserSummary]) -> Markup {
+    html! {
+        .admin {
+            h2 { "Users" }
+            table {
+                thead {
+                    tr {
+                        th { "Username" }
+                        th { "Tickets" }
+                        th { "Last login" }
+                        th { "Status" }
+                        th {}
+                    }
+                }
+                tbody {
+                    @for user in users {
+                        @let url = format!("/admin/users/{}", user.id);
+                        tr {
+                            td { (user.username) }
+                            td { (user.tickets) }
+                            td { (date(user.last_login_at.as_deref().unwrap_or("Never"))) }
+                            td {
+                                @if user.disabled_at.is_some() {
+                                    "Disabled"
+                                } @else if user.password_change_required_at.is_some() {
+                                    "Password change pending"
+                                } @else {
+                                    "Active"
+                                }
+                            }
+                            td {
+                                @if user.disabled_at.is_some() {
+                                    button hx-delete={ (url) "/disable" } hx-target="#main-content" { "Enable" }
+                                } @else {
+                                    button hx-post={ (url) "/disable" } hx-target="#main-content" { "Disable" }
+                                }
+                                button hx-post={ (url) "/password-change" } hx-target="#main-content" { "Require password change" }
+                                button hx-delete={ (url) "/sessions" } hx-target="#main-content" { "Sign out" }
+                                button hx-delete=(url) hx-target="#main-content"
+                                    hx-confirm={ "Delete " (user.username) " and all of their tickets?" } { "Delete" }
+                            }
+                        }
+                    }
+                }
+            }
+
+            p { a href="/admin/audit" { "View the audit log" } }
+        }
+    }
+}
+
+pub struct AuditFilter {
+    pub action: Option<Action>,
+    pub user: Option<UserId>,
+    pub ticket: Option<TicketId>,
+    pub page: u32,
+    /// Whether there's another page after this one.
+    pub more: bool,
+}
+
+impl AuditFilter {
+    /// The same search, on another page.
+    fn url(&self, page: u32) -> String {
+        let or_blank = |value: Option<String>| value.unwrap_or_default();
+        format!(
+            "/admin/audit?action={}&user={}&ticket={}&page={page}",
+            or_blank(self.action.map(|action| action.to_string())),
+            or_blank(self.user.map(|user| user.to_string())),
+            or_blank(self.ticket.map(|ticket| ticket.to_string())),
+        )
+    }
+}
+
+pub fn audit(events: &[AuditEvent], users: &[User], filter: AuditFilter) -> Markup {
+    let name = |id: UserId| {
+        users
+            .iter()
+            .find(|user| user.id == id)
+            .map_or_else(|| format!("#{id}"), |user| user.username.clone())
+    };
+
+    html! {
+        .admin {
+            h2 { "Audit log" }
+            form action="/admin/audit" method="get" {
+                label for="action" { "Action: " }
+                select name="action" {
+                    option value="" { "Any" }
+                    @for action in Action::ALL {
+                        option value=(action) selected[filter.action == Some(action)] { (action) }
+                    }
+                }
+
+                label for="user" { "User: " }
+                select name="user" {
+                    option value="" { "Anyone" }
+                    @for user in users {
+                        option value=(user.id) selected[filter.user == Some(user.id)] { (user.username) }
+                    }
+                }
+
+                label for="ticket" { "Ticket id: " }
+                input name="ticket" type="number" min="1" value=[filter.ticket];
+
+                input type="submit" value="Filter";
+            }
+
+            (super::audit::events(events, &name))
+
+            p {
+                @if filter.page > 1 {
+                    a href=(filter.url(filter.page - 1)) { "Newer" } " "
+                }
+                @if filter.more {
+                    a href=(filter.url(filter.page + 1)) { "Older" }
+                }
+            }
+        }
+    }
+}
+
+pub fn roles(users: &[User], roles: &[UserRole], configured_admins: &[UserId]) -> Markup {
+    html! {
+        .admin {
+            h2 { "Roles" }
+            table {
+                thead {
+                    tr {
+                        th { "Username" }
+                        @for role in Role::ALL {
+                            th { (role) }
+                        }
+                    }
+                }
+                tbody {
+                    @for user in users {
+                        tr {
+                            td { (user.username) }
+                            @for role in Role::ALL {
+                                @let url = format!("/admin/roles/{}/{}", user.id, role);
+                                td {
+                                    @if roles.iter().any(|r| r.user == user.id && r.role == role) {
+                                        button hx-delete=(url) hx-target="#main-content" { "Revoke" }
+                                    } @else if role == Role::Admin && configured_admins.contains(&user.id) {
+                                        small .sub { "Configured" }
+                                    } @else {
+                                        button hx-post=(url) hx-target="#main-content" { "Grant" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
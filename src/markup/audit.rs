@@ -0,0 +1,35 @@
+use maud::{html, Markup};
+
+use crate::models::{audit::AuditEvent, user::UserId};
+
+/// A table of audit events, newest first. `name` says who a user id is.
+pub fn events(events: &[AuditEvent], name: &dyn Fn(UserId) -> String) -> Markup {
+    html! {
+        table {
+            thead {
+                tr {
+                    th { "When" }
+                    th { "Who" }
+                    th { "Action" }
+                    th { "Account" }
+                    th { "Ticket" }
+                    th { "Details" }
+                    th { "IP" }
+                }
+            }
+            tbody {
+                @for event in events {
+                    tr {
+                        td { (event.created_at.get(..19).unwrap_or(&event.created_at)) }
+                        td { (event.actor.map_or_else(|| String::from("Nobody"), name)) }
+                        td { (event.action) }
+                        td { (event.target_user.map(name).unwrap_or_default()) }
+                        td { @if let Some(ticket) = event.target_ticket { (ticket) } }
+                        td { (event.detail.as_deref().unwrap_or("")) }
+                        td { (event.ip.as_deref().unwrap_or("")) }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! Charts drawn as inline SVG, so they arrive with the rest of the page and
+//! need no script.
+
+use maud::{html, Markup};
+use time::PrimitiveDateTime;
+
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 220.0;
+const PAD: f64 = 24.0;
+const LABEL_HEIGHT: f64 = 20.0;
+const ROW_HEIGHT: f64 = 26.0;
+/// Room for ticket titles to the left of horizontal bars.
+const ROW_LABEL_WIDTH: f64 = 180.0;
+
+pub struct Bar {
+    pub label: String,
+    pub value: u32,
+    /// Drawn faded, e.g. for an expired ticket.
+    pub muted: bool,
+}
+
+/// A span of time on a [`timeline`], like a ticket's validity.
+pub struct Span {
+    pub label: String,
+    pub start: PrimitiveDateTime,
+    pub end: PrimitiveDateTime,
+    pub muted: bool,
+}
+
+/// Coordinates to one decimal place, which is plenty for a `viewBox` this
+/// size.
+fn px(value: f64) -> String {
+    format!("{value:.1}")
+}
+
+fn bar_class(muted: bool) -> &'static str {
+    if muted {
+        "bar muted"
+    } else {
+        "bar"
+    }
+}
+
+fn figure(title: &str, height: f64, content: Markup) -> Markup {
+    html! {
+        figure .chart {
+            figcaption { (title) }
+            svg xmlns="http://www.w3.org/2000/svg" viewBox={ "0 0 " (px(WIDTH)) " " (px(height)) }
+                role="img" aria-label=(title) {
+                (content)
+            }
+        }
+    }
+}
+
+fn empty(message: &str, height: f64) -> Markup {
+    html! {
+        text .empty x=(px(WIDTH / 2.0)) y=(px(height / 2.0)) text-anchor="middle" { (message) }
+    }
+}
+
+/// Vertical bars, one per label, scaled to the tallest.
+pub fn columns(title: &str, bars: &[Bar], empty_message: &str) -> Markup {
+    let max = bars.iter().map(|bar| bar.value).max().unwrap_or(0);
+    let plot_height = HEIGHT - PAD - LABEL_HEIGHT;
+    let bottom = PAD + plot_height;
+    let slot = (WIDTH - 2.0 * PAD) / bars.len().max(1) as f64;
+
+    figure(
+        title,
+        HEIGHT,
+        html! {
+            @if max == 0 {
+                (empty(empty_message, HEIGHT))
+            } @else {
+                line .axis x1=(px(PAD)) y1=(px(bottom)) x2=(px(WIDTH - PAD)) y2=(px(bottom)) {}
+                @for (i, bar) in bars.iter().enumerate() {
+                    @let height = plot_height * f64::from(bar.value) / f64::from(max);
+                    @let x = PAD + slot * i as f64;
+                    @let centre = x + slot / 2.0;
+                    rect class=(bar_class(bar.muted))
+                        x=(px(x + slot * 0.15)) y=(px(bottom - height))
+                        width=(px(slot * 0.7)) height=(px(height)) {
+                        title { (bar.label) ": " (bar.value) }
+                    }
+                    @if bar.value > 0 {
+                        text .value x=(px(centre)) y=(px(bottom - height - 4.0)) text-anchor="middle" { (bar.value) }
+                    }
+                    text .label x=(px(centre)) y=(px(bottom + LABEL_HEIGHT - 6.0)) text-anchor="middle" { (bar.label) }
+                }
+            }
+        },
+    )
+}
+
+/// Horizontal bars, one row per label, for labels too long to sit under a
+/// column.
+pub fn rows(title: &str, bars: &[Bar], empty_message: &str) -> Markup {
+    let max = bars.iter().map(|bar| bar.value).max().unwrap_or(0);
+    let height = if bars.is_empty() {
+        HEIGHT / 2.0
+    } else {
+        2.0 * PAD + ROW_HEIGHT * bars.len() as f64
+    };
+    let plot_width = WIDTH - ROW_LABEL_WIDTH - 2.0 * PAD;
+
+    figure(
+        title,
+        height,
+        html! {
+            @if bars.is_empty() {
+                (empty(empty_message, height))
+            } @else {
+                @for (i, bar) in bars.iter().enumerate() {
+                    @let y = PAD + ROW_HEIGHT * i as f64;
+                    @let width = if max == 0 { 0.0 } else { plot_width * f64::from(bar.value) / f64::from(max) };
+                    text .label x=(px(ROW_LABEL_WIDTH)) y=(px(y + ROW_HEIGHT * 0.65)) text-anchor="end" { (bar.label) }
+                    rect class=(bar_class(bar.muted))
+                        x=(px(ROW_LABEL_WIDTH + 8.0)) y=(px(y + ROW_HEIGHT * 0.15))
+                        width=(px(width)) height=(px(ROW_HEIGHT * 0.7)) {
+                        title { (bar.label) ": " (bar.value) }
+                    }
+                    text .value x=(px(ROW_LABEL_WIDTH + 14.0 + width)) y=(px(y + ROW_HEIGHT * 0.65)) { (bar.value) }
+                }
+            }
+        },
+    )
+}
+
+/// Spans laid out against a shared time axis, one row each, so overlaps line
+/// up. `now` is marked when it falls inside.
+pub fn timeline(
+    title: &str,
+    spans: &[Span],
+    now: PrimitiveDateTime,
+    empty_message: &str,
+) -> Markup {
+    let from = spans.iter().map(|span| span.start).min();
+    let to = spans.iter().map(|span| span.end).max();
+    let height = if spans.is_empty() {
+        HEIGHT / 2.0
+    } else {
+        2.0 * PAD + ROW_HEIGHT * spans.len() as f64 + LABEL_HEIGHT
+    };
+    let plot_width = WIDTH - ROW_LABEL_WIDTH - 2.0 * PAD;
+    let date_format =
+        time::macros::format_description!("[day padding:none] [month repr:short] [year]");
+
+    let (Some(from), Some(to)) = (from, to) else {
+        return figure(title, height, empty(empty_message, height));
+    };
+    // a range of nothing still needs somewhere to draw
+    let range = (to - from).whole_seconds().max(1) as f64;
+    let x = |time: PrimitiveDateTime| {
+        ROW_LABEL_WIDTH + 8.0 + plot_width * (time - from).whole_seconds() as f64 / range
+    };
+    let bottom = PAD + ROW_HEIGHT * spans.len() as f64;
+
+    figure(
+        title,
+        height,
+        html! {
+            @for (i, span) in spans.iter().enumerate() {
+                @let y = PAD + ROW_HEIGHT * i as f64;
+                text .label x=(px(ROW_LABEL_WIDTH)) y=(px(y + ROW_HEIGHT * 0.65)) text-anchor="end" { (span.label) }
+                rect class=(bar_class(span.muted))
+                    x=(px(x(span.start))) y=(px(y + ROW_HEIGHT * 0.15))
+                    width=(px((x(span.end) - x(span.start)).max(1.0))) height=(px(ROW_HEIGHT * 0.7)) {
+                    title { (span.label) }
+                }
+            }
+            line .axis x1=(px(x(from))) y1=(px(bottom)) x2=(px(x(to))) y2=(px(bottom)) {}
+            text .label x=(px(x(from))) y=(px(bottom + LABEL_HEIGHT - 4.0)) { (from.format(date_format).unwrap()) }
+            text .label x=(px(x(to))) y=(px(bottom + LABEL_HEIGHT - 4.0)) text-anchor="end" { (to.format(date_format).unwrap()) }
+            @if from <= now && now <= to {
+                line .today x1=(px(x(now))) y1=(px(PAD / 2.0)) x2=(px(x(now))) y2=(px(bottom)) {}
+                text .label x=(px(x(now))) y=(px(PAD / 2.0 - 2.0)) text-anchor="middle" { "Today" }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn bar(label: &str, value: u32) -> Bar {
+        Bar {
+            label: label.to_owned(),
+            value,
+            muted: false,
+        }
+    }
+
+    fn span(label: &str, start: PrimitiveDateTime, end: PrimitiveDateTime) -> Span {
+        Span {
+            label: label.to_owned(),
+            start,
+            end,
+            muted: false,
+        }
+    }
+
+    fn rects(svg: &str) -> usize {
+        svg.matches("<rect").count()
+    }
+
+    /// One tag per line, so a snapshot that stops matching shows where.
+    fn pretty(chart: Markup) -> String {
+        chart.into_string().replace("><", ">\n<")
+    }
+
+    #[test]
+    fn columns_without_data_say_so() {
+        for bars in [vec![], vec![bar("Mon", 0), bar("Tue", 0)]] {
+            let svg = columns("Weekly", &bars, "Nothing yet").into_string();
+
+            assert!(svg.contains(r#"<text class="empty""#));
+            assert!(svg.contains("Nothing yet"));
+            assert_eq!(rects(&svg), 0);
+        }
+    }
+
+    #[test]
+    fn a_single_column_fills_the_plot() {
+        let svg = columns("Weekly", &[bar("Mon", 3)], "Nothing yet").into_string();
+
+        assert_eq!(rects(&svg), 1);
+        assert!(
+            svg.contains(r#"<rect class="bar" x="106.8" y="24.0" width="386.4" height="176.0">"#)
+        );
+        assert!(svg.contains("<title>Mon: 3</title>"));
+    }
+
+    #[test]
+    fn columns_scale_to_the_tallest() {
+        let svg =
+            columns("Weekly", &[bar("Mon", 2), bar("Tue", 4), bar("Wed", 0)], "").into_string();
+
+        assert_eq!(rects(&svg), 3);
+        assert!(svg.contains(r#"height="88.0""#));
+        assert!(svg.contains(r#"height="176.0""#));
+        assert!(svg.contains(r#"height="0.0""#));
+        // zero bars aren't labelled with their value
+        assert_eq!(svg.matches(r#"<text class="value""#).count(), 2);
+    }
+
+    #[test]
+    fn rows_without_data_say_so() {
+        let svg = rows("Tickets", &[], "No tickets").into_string();
+
+        assert!(svg.contains("No tickets"));
+        assert!(svg.contains(r#"viewBox="0 0 600.0 110.0""#));
+        assert_eq!(rects(&svg), 0);
+    }
+
+    #[test]
+    fn rows_scale_to_the_longest() {
+        let mut expired = bar("Old", 1);
+        expired.muted = true;
+        let svg = rows("Tickets", &[expired, bar("New", 4)], "").into_string();
+
+        assert!(svg.contains(r#"<rect class="bar muted" x="188.0" y="27.9" width="93.0""#));
+        assert!(svg.contains(r#"<rect class="bar" x="188.0" y="53.9" width="372.0""#));
+        assert!(svg.contains(r#"viewBox="0 0 600.0 100.0""#));
+    }
+
+    #[test]
+    fn timeline_without_data_says_so() {
+        let svg = timeline("Validity", &[], datetime!(2025-01-01 0:00), "No tickets").into_string();
+
+        assert!(svg.contains("No tickets"));
+        assert_eq!(rects(&svg), 0);
+    }
+
+    #[test]
+    fn a_single_span_fills_the_timeline() {
+        let svg = timeline(
+            "Validity",
+            &[span(
+                "Weekly",
+                datetime!(2025-01-06 0:00),
+                datetime!(2025-01-13 0:00),
+            )],
+            datetime!(2025-01-08 12:00),
+            "",
+        )
+        .into_string();
+
+        assert!(svg.contains(r#"x="188.0" y="27.9" width="372.0""#));
+        assert!(svg.contains("6 Jan 2025"));
+        assert!(svg.contains("13 Jan 2025"));
+        assert!(svg.contains(r#"<line class="today""#));
+    }
+
+    #[test]
+    fn timeline_spans_share_an_axis() {
+        let svg = timeline(
+            "Validity",
+            &[
+                span(
+                    "First",
+                    datetime!(2025-01-01 0:00),
+                    datetime!(2025-01-11 0:00),
+                ),
+                span(
+                    "Second",
+                    datetime!(2025-01-06 0:00),
+                    datetime!(2025-01-21 0:00),
+                ),
+            ],
+            datetime!(2025-03-01 0:00),
+            "",
+        )
+        .into_string();
+
+        assert!(svg.contains(r#"x="188.0" y="27.9" width="186.0""#));
+        assert!(svg.contains(r#"x="281.0" y="53.9" width="279.0""#));
+        // today is off the end
+        assert!(!svg.contains(r#"<line class="today""#));
+    }
+
+    // Snapshots of whole charts for a fixed dataset, to be updated on purpose
+    // whenever the output is meant to change.
+
+    #[test]
+    fn columns_snapshot() {
+        let bars = [bar("6 Jan", 2), bar("13 Jan", 5), bar("20 Jan", 0)];
+
+        assert_eq!(
+            pretty(columns("Journeys per week", &bars, "No journeys yet")),
+            r#"<figure class="chart">
+<figcaption>Journeys per week</figcaption>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 600.0 220.0" role="img" aria-label="Journeys per week">
+<line class="axis" x1="24.0" y1="200.0" x2="576.0" y2="200.0">
+</line>
+<rect class="bar" x="51.6" y="129.6" width="128.8" height="70.4">
+<title>6 Jan: 2</title>
+</rect>
+<text class="value" x="116.0" y="125.6" text-anchor="middle">2</text>
+<text class="label" x="116.0" y="214.0" text-anchor="middle">6 Jan</text>
+<rect class="bar" x="235.6" y="24.0" width="128.8" height="176.0">
+<title>13 Jan: 5</title>
+</rect>
+<text class="value" x="300.0" y="20.0" text-anchor="middle">5</text>
+<text class="label" x="300.0" y="214.0" text-anchor="middle">13 Jan</text>
+<rect class="bar" x="419.6" y="200.0" width="128.8" height="0.0">
+<title>20 Jan: 0</title>
+</rect>
+<text class="label" x="484.0" y="214.0" text-anchor="middle">20 Jan</text>
+</svg>
+</figure>"#
+        );
+    }
+
+    #[test]
+    fn rows_snapshot() {
+        let mut expired = bar("Old", 1);
+        expired.muted = true;
+
+        assert_eq!(
+            pretty(rows(
+                "Journeys per ticket",
+                &[expired, bar("Weekly", 4)],
+                "No tickets yet"
+            )),
+            r#"<figure class="chart">
+<figcaption>Journeys per ticket</figcaption>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 600.0 100.0" role="img" aria-label="Journeys per ticket">
+<text class="label" x="180.0" y="40.9" text-anchor="end">Old</text>
+<rect class="bar muted" x="188.0" y="27.9" width="93.0" height="18.2">
+<title>Old: 1</title>
+</rect>
+<text class="value" x="287.0" y="40.9">1</text>
+<text class="label" x="180.0" y="66.9" text-anchor="end">Weekly</text>
+<rect class="bar" x="188.0" y="53.9" width="372.0" height="18.2">
+<title>Weekly: 4</title>
+</rect>
+<text class="value" x="566.0" y="66.9">4</text>
+</svg>
+</figure>"#
+        );
+    }
+
+    #[test]
+    fn timeline_snapshot() {
+        let spans = [
+            span(
+                "First",
+                datetime!(2025-01-01 0:00),
+                datetime!(2025-01-11 0:00),
+            ),
+            span(
+                "Second",
+                datetime!(2025-01-06 0:00),
+                datetime!(2025-01-21 0:00),
+            ),
+        ];
+
+        assert_eq!(
+            pretty(timeline(
+                "Validity",
+                &spans,
+                datetime!(2025-01-08 12:00),
+                "No tickets yet"
+            )),
+            r#"<figure class="chart">
+<figcaption>Validity</figcaption>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 600.0 120.0" role="img" aria-label="Validity">
+<text class="label" x="180.0" y="40.9" text-anchor="end">First</text>
+<rect class="bar" x="188.0" y="27.9" width="186.0" height="18.2">
+<title>First</title>
+</rect>
+<text class="label" x="180.0" y="66.9" text-anchor="end">Second</text>
+<rect class="bar" x="281.0" y="53.9" width="279.0" height="18.2">
+<title>Second</title>
+</rect>
+<line class="axis" x1="188.0" y1="76.0" x2="560.0" y2="76.0">
+</line>
+<text class="label" x="188.0" y="92.0">1 Jan 2025</text>
+<text class="label" x="560.0" y="92.0" text-anchor="end">21 Jan 2025</text>
+<line class="today" x1="327.5" y1="12.0" x2="327.5" y2="76.0">
+</line>
+<text class="label" x="327.5" y="10.0" text-anchor="middle">Today</text>
+</svg>
+</figure>"#
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use maud::{html, Markup};
+use time::{
+    format_description::well_known::Iso8601, macros::format_description, Date, Duration, Month,
+    PrimitiveDateTime,
+};
+
+use super::chart::{self, Bar, Span};
+use crate::models::{self, journey::Journey, ticket::Ticket};
+
+/// How far back the weekly and monthly charts go.
+const WEEKS: usize = 12;
+const MONTHS: usize = 12;
+
+/// Charts of a user's journeys. Journeys are counted once each, however
+/// many of their tickets overlapped at the time.
+pub fn dashboard(tickets: &[Ticket], journeys: &[Journey], now: PrimitiveDateTime) -> Markup {
+    let taken = journeys
+        .iter()
+        .map(|journey| {
+            models::uk_time(
+                PrimitiveDateTime::parse(&journey.taken_at, &Iso8601::DEFAULT)
+                    .expect("taken_at should be in the correct format in DB"),
+            )
+            .date()
+        })
+        .collect::<Vec<_>>();
+
+    html! {
+        .dashboard {
+            h2 { "Dashboard" }
+            (chart::columns("Journeys per week", &per_week(&taken, now.date()), "No journeys in the last 12 weeks"))
+            (chart::columns("Journeys per month", &per_month(&taken, now.date()), "No journeys in the last year"))
+            (chart::rows("Journeys per ticket", &per_ticket(tickets, now), "No tickets yet"))
+            (chart::timeline("When your tickets are valid", &validity(tickets, now), now, "No tickets yet"))
+        }
+    }
+}
+
+fn monday(date: Date) -> Date {
+    date - Duration::days(date.weekday().number_days_from_monday().into())
+}
+
+fn per_week(taken: &[Date], today: Date) -> Vec<Bar> {
+    let format = format_description!("[day padding:none] [month repr:short]");
+    let this_week = monday(today);
+
+    (0..WEEKS)
+        .rev()
+        .map(|weeks_ago| {
+            let week = this_week - Duration::weeks(weeks_ago as i64);
+            let count = taken.iter().filter(|date| monday(**date) == week).count();
+            Bar {
+                label: week.format(format).unwrap(),
+                value: count as u32,
+                muted: false,
+            }
+        })
+        .collect()
+}
+
+fn per_month(taken: &[Date], today: Date) -> Vec<Bar> {
+    let mut months = Vec::with_capacity(MONTHS);
+    let (mut year, mut month) = (today.year(), today.month());
+    for _ in 0..MONTHS {
+        months.push((year, month));
+        if month == Month::January {
+            year -= 1;
+        }
+        month = month.previous();
+    }
+
+    months
+        .into_iter()
+        .rev()
+        .map(|(year, month)| {
+            let count = taken
+                .iter()
+                .filter(|date| date.year() == year && date.month() == month)
+                .count();
+            // Jan gets its year so the turn of the year is clear
+            let label = if month == Month::January {
+                format!("Jan {}", year % 100)
+            } else {
+                month.to_string()[..3].to_owned()
+            };
+            Bar {
+                label,
+                value: count as u32,
+                muted: false,
+            }
+        })
+        .collect()
+}
+
+fn per_ticket(tickets: &[Ticket], now: PrimitiveDateTime) -> Vec<Bar> {
+    tickets
+        .iter()
+        .map(|ticket| Bar {
+            label: label(ticket, now),
+            value: ticket.usages,
+            muted: ticket.expiry <= now,
+        })
+        .collect()
+}
+
+fn validity(tickets: &[Ticket], now: PrimitiveDateTime) -> Vec<Span> {
+    let mut spans = tickets
+        .iter()
+        .map(|ticket| Span {
+            label: label(ticket, now),
+            start: ticket.start,
+            end: ticket.expiry,
+            muted: ticket.expiry <= now,
+        })
+        .collect::<Vec<_>>();
+    spans.sort_by_key(|span| span.start);
+    spans
+}
+
+/// A ticket's title, cut short to fit beside a bar.
+fn label(ticket: &Ticket, now: PrimitiveDateTime) -> String {
+    const MAX: usize = 24;
+
+    let mut label = if ticket.title.chars().count() > MAX {
+        let title = ticket.title.chars().take(MAX - 1).collect::<String>();
+        format!("{}…", title.trim_end())
+    } else {
+        ticket.title.clone()
+    };
+    if ticket.expiry <= now {
+        label.push_str(" (expired)");
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+
+    use super::*;
+    use crate::models::{
+        money::Money,
+        ticket::{DefId, Mode, Passenger, TicketId},
+    };
+
+    fn ticket(title: &str, expiry: PrimitiveDateTime) -> Ticket {
+        Ticket {
+            id: TicketId(1),
+            def: DefId(1),
+            title: title.to_owned(),
+            price: Money::new(1000, Default::default()),
+            start: datetime!(2025-01-01 0:00),
+            expiry,
+            mode: Mode::Bus,
+            passenger: Passenger::Adult,
+            qr: String::new(),
+            single_fare: None,
+            replaces: None,
+            notes: None,
+            archived_at: None,
+            deleted_at: None,
+            usages: 0,
+        }
+    }
+
+    fn values(bars: &[Bar]) -> Vec<u32> {
+        bars.iter().map(|bar| bar.value).collect()
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        // a Sunday, then the Monday after it
+        let taken = [
+            date!(2025 - 01 - 12),
+            date!(2025 - 01 - 13),
+            date!(2025 - 01 - 15),
+        ];
+        let weeks = per_week(&taken, date!(2025 - 01 - 16));
+
+        assert_eq!(weeks.len(), WEEKS);
+        assert_eq!(weeks[WEEKS - 1].label, "13 Jan");
+        assert_eq!(weeks[WEEKS - 2].label, "6 Jan");
+        assert_eq!(values(&weeks[WEEKS - 2..]), [1, 2]);
+    }
+
+    #[test]
+    fn months_cross_the_new_year() {
+        let taken = [
+            date!(2024 - 12 - 31),
+            date!(2025 - 01 - 01),
+            date!(2024 - 01 - 31),
+        ];
+        let months = per_month(&taken, date!(2025 - 01 - 20));
+
+        assert_eq!(months.len(), MONTHS);
+        assert_eq!(months[0].label, "Feb");
+        assert_eq!(months[MONTHS - 2].label, "Dec");
+        assert_eq!(months[MONTHS - 1].label, "Jan 25");
+        // a year ago is out of range
+        assert_eq!(values(&months).iter().sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn labels_are_cut_short_and_marked_expired() {
+        let now = datetime!(2025-02-01 0:00);
+        let long = ticket("Bee Network Bus Weekly Adult", datetime!(2025-01-08 0:00));
+        let current = ticket("Weekly", datetime!(2025-03-01 0:00));
+
+        assert_eq!(label(&long, now), "Bee Network Bus Weekly… (expired)");
+        assert_eq!(label(&current, now), "Weekly");
+        assert!(per_ticket(&[long], now)[0].muted);
+    }
+
+    #[test]
+    fn an_empty_dashboard_renders() {
+        let page = dashboard(&[], &[], datetime!(2025-01-01 0:00)).into_string();
+
+        assert!(page.contains("No journeys in the last 12 weeks"));
+        assert!(page.contains("No tickets yet"));
+    }
+}
@@ -1,3 +1,8 @@
+pub mod account;
+pub mod admin;
+mod audit;
+mod chart;
+pub mod dashboard;
 mod landing;
 mod ticket;
 
@@ -21,16 +26,29 @@ pub fn root(user: Option<User>) -> Markup {
     }
 }
 
+/// A whole page around `content`, for routes that are navigated to directly.
+pub fn page(user: Option<&User>, content: Markup) -> Markup {
+    html! {
+        (head())
+        (user_header(user))
+        #main-content {
+            (content)
+        }
+    }
+}
+
 pub fn head() -> Markup {
     html! {
         (DOCTYPE)
         head {
             meta charset="utf-8";
             meta name="viewport" content="width=device-width, initial-scale=1, maximum-scale=1";
+            // swap error responses too, they carry a message for the user
+            meta name="htmx-config" content=r#"{"responseHandling":[{"code":"204","swap":false},{"code":"[23]..","swap":true},{"code":"[45]..","swap":true,"error":true}]}"#;
 
             title { "Bee Network Tracker" }
 
-            link rel="stylesheet" href="main.css";
+            link rel="stylesheet" href="/main.css";
             link rel="preconnect" href="https://fonts.googleapis.com";
             link rel="preconnect" href="https://fonts.gstatic.com" crossorigin;
             link href="https://fonts.googleapis.com/css2?family=Bricolage+Grotesque:opsz,wght@12..96,200..800&family=Inter:ital,opsz,wght@0,14..32,100..900;1,14..32,100..900&display=swap" rel="stylesheet";
@@ -38,7 +56,7 @@ pub fn head() -> Markup {
             link rel="stylesheet" href="/fontawesome/css/fontawesome.css";
 
             script src="https://unpkg.com/htmx.org@2.0.4" {};
-            script src="helpers.js" {};
+            script src="/helpers.js" {};
         }
     }
 }
@@ -59,6 +77,8 @@ pub fn user_header(user: Option<&User>) -> Markup {
                     }
                     .spaced {
                         a hx-get="/tickets/add" hx-target="#main-content" { "Add Ticket" }
+                        a href="/dashboard" { "Dashboard" }
+                        a href="/account" { "Account" }
                         a hx-get="/auth/logout" hx-target="body" { "Logout" }
                     }
                 }
@@ -81,3 +101,13 @@ pub fn user_header(user: Option<&User>) -> Markup {
         },
     }
 }
+
+/// Just the date part of a stored timestamp.
+fn date(timestamp: &str) -> &str {
+    timestamp.split('T').next().unwrap_or(timestamp)
+}
+
+/// A stored timestamp cut down to what a `datetime-local` input takes.
+fn minutes(timestamp: &str) -> &str {
+    timestamp.get(..16).unwrap_or(timestamp)
+}
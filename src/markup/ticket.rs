@@ -1,41 +1,92 @@
+use itertools::Itertools;
 use maud::{html, Markup};
-use time::{PrimitiveDateTime, UtcDateTime};
+use time::{
+    format_description::well_known::Iso8601, macros::format_description, Date, Duration,
+    PrimitiveDateTime,
+};
 
-use crate::models::ticket::{Ticket, TicketDef};
+use crate::models::{
+    self,
+    journey::Journey,
+    money::Money,
+    stats::Stats,
+    ticket::{self, DefId, Ticket, TicketDef, Validity},
+};
+
+/// The rider's current tickets, with any they've since replaced, renewed or
+/// archived tucked away underneath. `deleted` tickets can still be restored.
+pub fn ticket_area(owned_tickets: &[Ticket], deleted: &[Ticket]) -> Markup {
+    let (archived, unarchived): (Vec<_>, Vec<_>) = owned_tickets
+        .iter()
+        .partition(|ticket| ticket.is_archived());
+    let (earlier, current): (Vec<_>, Vec<_>) = unarchived
+        .into_iter()
+        .partition(|ticket| owned_tickets.iter().any(|t| t.replaces == Some(ticket.id)));
 
-pub fn ticket_area(owned_tickets: &[Ticket]) -> Markup {
     html! {
         {
             #ticket-area {
-                @for ticket in owned_tickets.iter() {
+                @for ticket in current {
                     (ticket_card(TicketMarkup::Small { ticket }))
                 }
+                @if !earlier.is_empty() {
+                    details .earlier-tickets {
+                        summary { "Earlier tickets (" (earlier.len()) ")" }
+                        @for ticket in earlier {
+                            (ticket_card(TicketMarkup::Small { ticket }))
+                        }
+                    }
+                }
+                @if !archived.is_empty() {
+                    details .earlier-tickets {
+                        summary { "Archived (" (archived.len()) ")" }
+                        @for ticket in archived {
+                            (ticket_card(TicketMarkup::Small { ticket }))
+                        }
+                    }
+                }
+                @if !deleted.is_empty() {
+                    section .deleted-tickets {
+                        h4 { "Recently deleted" }
+                        ul {
+                            @for ticket in deleted {
+                                li {
+                                    (ticket.title)
+                                    @if let Some(until) = restorable_until(ticket) {
+                                        " " small .sub { "until " (until) }
+                                    }
+                                    button hx-post={ "/tickets/" (ticket.id) "/restore" } hx-target="#tickets" {
+                                        "Restore"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
             }
         }
     }
 }
 
-pub fn ticket_form(owned_tickets: &[Ticket], defs: &[TicketDef]) -> Option<Markup> {
-    let unclaimed_ticket_defs = defs
-        .iter()
-        .filter(|t| !owned_tickets.iter().any(|ot| ot.def == t.id))
-        .collect::<Vec<_>>();
-    if !unclaimed_ticket_defs.is_empty() {
-        Some(html! {
-            form hx-post="/tickets/add" hx-target="body" {
-                label for="ticket" {"Ticket: "}
-                select id="ticket" name="ticket" {
-                    @for def in unclaimed_ticket_defs {
-                        option value=(def.id) { (def.title) }
-                    }
-                }
+/// When a deleted ticket will be removed for good.
+fn restorable_until(ticket: &Ticket) -> Option<String> {
+    let format = format_description!("[day padding:none] [month repr:long]");
+    let deleted_at =
+        PrimitiveDateTime::parse(ticket.deleted_at.as_deref()?, &Iso8601::DEFAULT).ok()?;
 
-                br;
-
-                label for="qr" {"QR Data "}
-                input name="qr" type="text" placeholder="7,1,47,128c93669b...";
+    models::uk_time(deleted_at + ticket::DELETE_GRACE)
+        .format(format)
+        .ok()
+}
 
-                br;
+/// Riders can hold several of the same ticket, e.g. a replacement for a
+/// lost one, so every definition still on sale is offered.
+pub fn ticket_form(defs: &[TicketDef]) -> Option<Markup> {
+    let on_sale = defs.iter().filter(|t| !t.is_retired()).collect::<Vec<_>>();
+    if !on_sale.is_empty() {
+        Some(html! {
+            form hx-post="/tickets/add" hx-target="body" {
+                (ticket_fields(&on_sale, None))
 
                 input type="submit" value="Add";
             }
@@ -45,21 +96,78 @@ pub fn ticket_form(owned_tickets: &[Ticket], defs: &[TicketDef]) -> Option<Marku
     }
 }
 
+/// Adding a ticket in place of `ticket`, defaulting to the same one.
+pub fn renew_form(ticket: &Ticket, defs: &[TicketDef]) -> Markup {
+    let on_sale = defs.iter().filter(|t| !t.is_retired()).collect::<Vec<_>>();
+
+    html! {
+        .history {
+            header {
+                h3 { "Replace or renew" }
+                button .close-button hx-get={ "/tickets/" (ticket.id) "/history" } hx-target="#ticket-area" { "Back" }
+            }
+            p {
+                "The new ticket takes the place of " (ticket.title)
+                ", which moves to your earlier tickets with its journeys."
+            }
+            @if on_sale.is_empty() {
+                p { small .sub { "There are no tickets on sale right now." } }
+            } @else {
+                form hx-post={ "/tickets/" (ticket.id) "/renew" } hx-target="body" {
+                    (ticket_fields(&on_sale, Some(ticket.def)))
+
+                    input type="submit" value="Add";
+                }
+            }
+        }
+    }
+}
+
+fn ticket_fields(defs: &[&TicketDef], selected: Option<DefId>) -> Markup {
+    html! {
+        label for="ticket" {"Ticket: "}
+        select id="ticket" name="ticket" {
+            @for def in defs {
+                option value=(def.id) selected[selected == Some(def.id)] { (def.title) " — " (def.price) }
+            }
+        }
+
+        br;
+
+        label for="qr" {"QR Data "}
+        input name="qr" type="text" placeholder="7,1,47,128c93669b...";
+
+        br;
+    }
+}
+
 pub enum TicketMarkup<'t> {
-    Large { ticket: &'t Ticket },
-    Small { ticket: &'t Ticket },
+    /// `validation` is the tap to validate control, or `None` for users who
+    /// count a journey whenever they close the card.
+    Large {
+        ticket: &'t Ticket,
+        validation: Option<Markup>,
+    },
+    Small {
+        ticket: &'t Ticket,
+    },
 }
 
 pub fn ticket_card(markup: TicketMarkup) -> Markup {
     match markup {
-        TicketMarkup::Large { ticket } => large(ticket),
+        TicketMarkup::Large { ticket, validation } => large(ticket, validation),
         TicketMarkup::Small { ticket } => small(ticket),
     }
 }
 
-fn large(ticket: &Ticket) -> Markup {
+fn large(ticket: &Ticket, validation: Option<Markup>) -> Markup {
     let ticket_qr = format!("/qr?ticket={}", ticket.id);
-    let increment = format!("increment({})", ticket.id);
+    let increment = validation
+        .is_none()
+        .then(|| format!("increment({}, '{}')", ticket.id, uuid::Uuid::new_v4()));
+    let now = models::local_now();
+    let stats = Stats::new(ticket, now);
+    let validity = ticket.validity(now);
 
     html! {
         @let save_svg = format!("save_svg(event, {})", 1);
@@ -67,22 +175,17 @@ fn large(ticket: &Ticket) -> Markup {
         .large-ticket {
             header {
                 h3 { "Your Ticket"}
-                button .close-button hx-get="/tickets" hx-target="#tickets" hx-on::before-send=(increment) { "Close" }
+                button .close-button hx-get="/tickets" hx-target="#tickets" hx-on::before-send=[increment] { "Close" }
             }
-            .ticket-card style="margin-top: 1em; margin-bottom: 1em" {
-                header {
-                    div {
-                        i .fa-sm .fa-solid .fa-bus-simple {}
-                        small style="padding-inline: 0.5em;" { "Bus" }
-                    }
-                    div {
-                        i .fa-sm .fa-solid .fa-user {}
-                        small style="padding-inline: 0.5em;" { "Student" }
-                    }
-                }
+            .ticket-card .expired[validity == Validity::Expired] .not-yet-valid[validity == Validity::NotYetValid] style="margin-top: 1em; margin-bottom: 1em" {
+                (card_header(ticket))
+                (validity_note(validity))
                 main {
                     #qr hx-get=(ticket_qr) hx-trigger="load" hx-on::after-settle=(save_svg) {}
                     (expiry(&ticket.expiry, true))
+                    @if let Some(validation) = validation {
+                        (validation)
+                    }
                     .moving-bee {
                         hr;
                         .bee-container {
@@ -93,7 +196,16 @@ fn large(ticket: &Ticket) -> Markup {
                     }
                     div {
                         h3 { (ticket.title) }
-                        small .sub { "Students must show valid ID on use" }
+                        p .price {
+                            (ticket.price)
+                            @if let Some(each) = stats.cost_per_journey {
+                                " " small .sub { "(" (each) " a journey)" }
+                            }
+                        }
+                        (payoff(ticket, &stats))
+                        @if let Some(proof) = ticket.passenger.proof() {
+                            small .sub { (proof) }
+                        }
                         div style="line-height: 1.5" {
                             p {
                                 i .fa-sm .fa-solid .fa-circle-check .fa-fw style="padding-right: 0.5em" {}
@@ -109,7 +221,7 @@ fn large(ticket: &Ticket) -> Markup {
                 div style="padding-top: 1.5em" { hr; }
                 footer {
                     div {
-                        p {
+                        p hx-get={ "/tickets/" (ticket.id) "/history" } hx-target="#ticket-area" style="cursor: pointer" {
                             i .fa-sm .fa-solid .fa-circle-info .fa-fw style="padding-right: 0.5em" {}
                             small { "View details" }
                         }
@@ -122,23 +234,16 @@ fn large(ticket: &Ticket) -> Markup {
 
 fn small(ticket: &Ticket) -> Markup {
     let large_ticket = format!("/tickets/{}", ticket.id);
+    let validity = ticket.validity(models::local_now());
 
     html! {
-        .ticket-card hx-get=(large_ticket) hx-trigger="click" hx-target="#ticket-area" {
-            header {
-                div {
-                    i .fa-sm .fa-solid .fa-bus-simple {}
-                    small style="padding-inline: 0.5em;" { "Bus" }
-                }
-                div {
-                    i .fa-sm .fa-solid .fa-user {}
-                    small style="padding-inline: 0.5em;" { "Student" }
-                }
-            }
+        .ticket-card .expired[validity == Validity::Expired] .not-yet-valid[validity == Validity::NotYetValid] hx-get=(large_ticket) hx-trigger="click" hx-target="#ticket-area" {
+            (card_header(ticket))
+            (validity_note(validity))
             main {
                 div {
                     h3 { (ticket.title) }
-                    small .sub { "Bee Network Bus" }
+                    small .sub { "Bee Network " (ticket.mode.label()) }
                 }
                 div style="width: 100%; margin-inline: 0" { hr; }
                 (expiry(&ticket.expiry, false))
@@ -152,9 +257,145 @@ fn small(ticket: &Ticket) -> Markup {
     }
 }
 
+/// Whether the ticket has beaten buying singles yet, or how far off it is.
+fn payoff(ticket: &Ticket, stats: &Stats) -> Markup {
+    html! {
+        @if let Some(saved) = stats.saved {
+            p { small { "Paid off, " (saved) " saved on singles" } }
+        } @else if let Some(to_go) = stats.journeys_to_break_even(ticket.usages) {
+            p {
+                small {
+                    (to_go) " more " @if to_go == 1 { "journey" } @else { "journeys" }
+                    " to beat " (stats.single_fare) " singles"
+                }
+            }
+        }
+    }
+}
+
+/// Value for money, at the top of the details page.
+fn stats(ticket: &Ticket, error: Option<&str>) -> Markup {
+    let stats = Stats::new(ticket, models::local_now());
+    let or_dash =
+        |money: Option<Money>| money.map_or_else(|| String::from("–"), |money| money.to_string());
+
+    html! {
+        section .stats {
+            dl {
+                dt { "Paid" }
+                dd { (ticket.price) }
+                dt { "Cost per journey so far" }
+                dd { (or_dash(stats.cost_per_journey)) }
+                dt { "Cost per journey by expiry" }
+                dd {
+                    (or_dash(stats.projected_cost_per_journey))
+                    @if let Some(projected) = stats.projected_journeys {
+                        " " small .sub { "at this rate, " (projected) " journeys" }
+                    }
+                }
+                dt { "Days remaining" }
+                dd { (stats.days_remaining) }
+                dt { "Break even" }
+                dd {
+                    @if let Some(break_even) = stats.break_even {
+                        (break_even) " journeys at " (stats.single_fare)
+                    } @else {
+                        "–"
+                    }
+                    " " (payoff(ticket, &stats))
+                }
+            }
+
+            form #single-fare hx-put={ "/tickets/" (ticket.id) "/fare" } hx-target="#ticket-area" {
+                @if let Some(error) = error {
+                    p .error { (error) }
+                }
+
+                label for="single_fare" { "Your single fare: " }
+                input name="single_fare" type="text" inputmode="decimal"
+                    placeholder=(stats.single_fare.plain())
+                    value=[ticket.single_fare.map(|fare| fare.plain())];
+                input type="submit" value="Save";
+            }
+        }
+    }
+}
+
+/// Tap to validate on the large card. Straight after a validation it shows
+/// when instead, with an undo button for the `undo_left` that remains.
+pub fn validation(
+    ticket: &Ticket,
+    latest: Option<&Journey>,
+    undo_left: Option<Duration>,
+) -> Markup {
+    let time_format =
+        format_description!("[hour repr:12 padding:none]:[minute][period case:lower]");
+    let url = format!("/tickets/{}/validate", ticket.id);
+    let date_format = format_description!("[day padding:none] [month repr:short]");
+    let taken_at = latest.map(taken_at);
+
+    html! {
+        #validate {
+            @match (undo_left, taken_at) {
+                (Some(undo_left), Some(taken_at)) => {
+                    p .validated {
+                        i .fa-solid .fa-circle-check style="padding-right: 0.5em" {}
+                        "Validated at " (taken_at.format(time_format).unwrap())
+                    }
+                    button hx-post={ (url) "/undo" } hx-target="#validate" hx-swap="outerHTML" { "Undo" }
+                    // swaps the undo button away once it would be refused
+                    div hx-get=(url) hx-trigger={ "load delay:" (undo_left.whole_seconds().max(1)) "s" }
+                        hx-target="#validate" hx-swap="outerHTML" {}
+                }
+                (_, taken_at) => {
+                    @if ticket.validity(models::local_now()) == Validity::Valid {
+                        button .validate hx-post=(url) hx-target="#validate" hx-swap="outerHTML"
+                            hx-headers={ r#"{"Idempotency-Key": ""# (uuid::Uuid::new_v4()) r#""}"# } {
+                            "Tap to validate"
+                        }
+                    }
+                    @if let Some(taken_at) = taken_at {
+                        p { small .sub {
+                            "Last validated " (taken_at.format(date_format).unwrap())
+                            " at " (taken_at.format(time_format).unwrap())
+                        } }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A banner on cards that can't be used right now.
+fn validity_note(validity: Validity) -> Markup {
+    html! {
+        @match validity {
+            Validity::NotYetValid => p .validity { small { "Not valid yet" } },
+            Validity::Valid => {},
+            Validity::Expired => p .validity { small { "Expired" } },
+        }
+    }
+}
+
+/// The mode and passenger type across the top of a card.
+fn card_header(ticket: &Ticket) -> Markup {
+    html! {
+        header {
+            div {
+                i .fa-sm .fa-solid .(ticket.mode.icon()) {}
+                small style="padding-inline: 0.5em;" { (ticket.mode.label()) }
+            }
+            div {
+                i .fa-sm .fa-solid .(ticket.passenger.icon()) {}
+                small style="padding-inline: 0.5em;" { (ticket.passenger.label()) }
+            }
+        }
+    }
+}
+
 fn expiry(expiry: &PrimitiveDateTime, fullscreen: bool) -> Markup {
     let expiry = {
-        let now: time::PrimitiveDateTime = unsafe { core::mem::transmute(UtcDateTime::now()) };
+        let now = models::local_now();
         let two_weeks_prior = *expiry - time::Duration::weeks(2);
 
         if now >= *expiry {
@@ -184,3 +425,201 @@ fn expiry(expiry: &PrimitiveDateTime, fullscreen: bool) -> Markup {
         }
     }
 }
+
+/// Journeys on `ticket`, most recent first, grouped into weeks and then
+/// days.
+pub fn history(ticket: &Ticket, journeys: &[Journey], fare_error: Option<&str>) -> Markup {
+    let week_format = format_description!("[day padding:none] [month repr:long] [year]");
+    let day_format = format_description!("[weekday] [day padding:none] [month repr:long]");
+
+    html! {
+        .history {
+            header {
+                h3 { (ticket.title) }
+                button .close-button hx-get={ "/tickets/" (ticket.id) } hx-target="#ticket-area" { "Back" }
+            }
+            @if let Some(notes) = &ticket.notes {
+                p .notes { (notes) }
+            }
+            (stats(ticket, fare_error))
+
+            p {
+                @if let Some(replaces) = ticket.replaces {
+                    a hx-get={ "/tickets/" (replaces) "/history" } hx-target="#ticket-area" href="#" {
+                        small { "See the ticket this replaced" }
+                    }
+                    " · "
+                }
+                a hx-get={ "/tickets/" (ticket.id) "/renew" } hx-target="#ticket-area" href="#" {
+                    small { "Replace or renew" }
+                }
+                " · "
+                a hx-get={ "/tickets/" (ticket.id) "/edit" } hx-target="#ticket-area" href="#" {
+                    small { "Edit" }
+                }
+                " · "
+                @if ticket.is_archived() {
+                    a hx-delete={ "/tickets/" (ticket.id) "/archive" } hx-target="#ticket-area" href="#" {
+                        small { "Unarchive" }
+                    }
+                } @else {
+                    a hx-post={ "/tickets/" (ticket.id) "/archive" } hx-target="#ticket-area" href="#" {
+                        small { "Archive" }
+                    }
+                }
+                " · "
+                a hx-post={ "/tickets/" (ticket.id) "/delete" } hx-target="#tickets" href="#"
+                    hx-confirm={
+                        "Delete this ticket and its journeys? You can restore it for "
+                        (ticket::DELETE_GRACE.whole_days()) " days."
+                    } {
+                    small { "Delete" }
+                }
+            }
+
+            p { (ticket.usages) @if ticket.usages == 1 { " journey" } @else { " journeys" } }
+
+            @if journeys.is_empty() {
+                p { small .sub { "No journeys yet." } }
+            }
+
+            @for (week, days) in by_week(journeys) {
+                section .week {
+                    h4 {
+                        "Week of " (week.format(week_format).unwrap())
+                        " " small .sub { "(" (days.iter().map(|(_, day)| day.len()).sum::<usize>()) ")" }
+                    }
+                    @for (day, journeys) in days {
+                        h5 { (day.format(day_format).unwrap()) }
+                        ul .journeys {
+                            @for journey in journeys {
+                                (journey_row(journey))
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A day's journeys.
+type Day<'j> = (Date, Vec<&'j Journey>);
+
+/// Groups journeys, already in order, by the Monday of their week and then
+/// by day.
+fn by_week(journeys: &[Journey]) -> Vec<(Date, Vec<Day<'_>>)> {
+    let by_day = journeys.iter().chunk_by(|journey| taken_at(journey).date());
+    let days = by_day
+        .into_iter()
+        .map(|(day, journeys)| (day, journeys.collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+
+    let by_week = days
+        .into_iter()
+        .chunk_by(|(day, _)| *day - Duration::days(day.weekday().number_days_from_monday().into()));
+    by_week
+        .into_iter()
+        .map(|(week, days)| (week, days.collect()))
+        .collect()
+}
+
+/// When a journey was taken, on the network's clock.
+fn taken_at(journey: &Journey) -> PrimitiveDateTime {
+    models::uk_time(
+        PrimitiveDateTime::parse(&journey.taken_at, &Iso8601::DEFAULT)
+            .expect("taken_at should be in the correct format in DB"),
+    )
+}
+
+fn journey_url(journey: &Journey) -> String {
+    format!("/tickets/{}/journeys/{}", journey.ticket, journey.id)
+}
+
+fn journey_row(journey: &Journey) -> Markup {
+    let time_format =
+        format_description!("[hour repr:12 padding:none]:[minute][period case:lower]");
+    let url = journey_url(journey);
+    let id = format!("journey-{}", journey.id);
+
+    html! {
+        li #(id) {
+            div {
+                i .fa-sm .fa-solid .fa-clock style="padding-right: 0.5em" {}
+                (taken_at(journey).format(time_format).unwrap())
+                @if let Some(route) = &journey.route {
+                    " · " (route)
+                }
+                @if let Some(stop) = &journey.stop {
+                    " from " (stop)
+                }
+                @if let Some(notes) = &journey.notes {
+                    br;
+                    small .sub { (notes) }
+                }
+            }
+            div {
+                button hx-get=(url) hx-target={ "#" (id) } hx-swap="outerHTML" { "Edit" }
+                button hx-delete=(url) hx-target="#ticket-area"
+                    hx-confirm="Delete this journey? It won't count towards the ticket any more." { "Delete" }
+            }
+        }
+    }
+}
+
+/// Correcting a ticket's QR data and notes, in place of its details.
+pub fn ticket_edit_form(ticket: &Ticket, error: Option<&str>) -> Markup {
+    html! {
+        .history {
+            header {
+                h3 { "Edit " (ticket.title) }
+                button .close-button hx-get={ "/tickets/" (ticket.id) "/history" } hx-target="#ticket-area" { "Back" }
+            }
+            form hx-put={ "/tickets/" (ticket.id) "/edit" } hx-target="#ticket-area" {
+                @if let Some(error) = error {
+                    p .error { (error) }
+                }
+
+                label for="qr" { "QR Data " }
+                input name="qr" type="text" required value=(ticket.qr);
+
+                br;
+
+                label for="notes" { "Notes: " }
+                input name="notes" type="text" value=[ticket.notes.as_deref()];
+
+                br;
+
+                input type="submit" value="Save";
+            }
+        }
+    }
+}
+
+/// Edits a journey in place of its row in the history.
+pub fn journey_form(journey: &Journey, error: Option<&str>) -> Markup {
+    // what a `datetime-local` input takes, on the same clock as the history
+    let input_format = format_description!("[year]-[month]-[day]T[hour]:[minute]");
+    let taken_at = taken_at(journey).format(input_format).unwrap();
+
+    html! {
+        li #{ "journey-" (journey.id) } {
+            form hx-put=(journey_url(journey)) hx-target="#ticket-area" {
+                @if let Some(error) = error {
+                    p .error { (error) }
+                }
+
+                label for="taken_at" { "Time: " }
+                input name="taken_at" type="datetime-local" required value=(taken_at);
+
+                label for="notes" { "Notes: " }
+                input name="notes" type="text" value=[journey.notes.as_deref()];
+
+                input type="submit" value="Save";
+                button type="button" hx-get={ "/tickets/" (journey.ticket) "/history" } hx-target="#ticket-area" {
+                    "Cancel"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{database, models::user::UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ApiTokenId(pub u32);
+
+impl From<ApiTokenId> for database::Binding {
+    fn from(val: ApiTokenId) -> Self {
+        database::Binding::from(val.0)
+    }
+}
+
+/// What an API token is allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    /// Read-only requests.
+    #[serde(rename = "tickets:read")]
+    Read,
+    /// Requests that change something, like recording a journey.
+    #[serde(rename = "tickets:write")]
+    Write,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [Scope::Read, Scope::Write];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "tickets:read",
+            Scope::Write => "tickets:write",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Scope::Read => "View tickets and usage",
+            Scope::Write => "Record and undo journeys, add tickets",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or(())
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: ApiTokenId,
+    pub user: UserId,
+    pub name: String,
+    /// SHA-256 of the token, the token itself is only shown once.
+    pub token_hash: String,
+    /// Space separated [`Scope`]s.
+    pub scopes: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl ApiToken {
+    pub fn scopes(&self) -> Vec<Scope> {
+        self.scopes
+            .split_whitespace()
+            .filter_map(|scope| scope.parse().ok())
+            .collect()
+    }
+}
+
+pub struct Insert {
+    pub user: UserId,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: String,
+    pub created_at: String,
+}
+
+impl database::Query for Insert {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO api_tokens (user, name, token_hash, scopes, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.user.into(),
+            self.name.as_str().into(),
+            self.token_hash.as_str().into(),
+            self.scopes.as_str().into(),
+            self.created_at.as_str().into(),
+        ]
+    }
+}
+
+/// Finds a token that hasn't been revoked.
+pub struct GetByHash {
+    pub token_hash: String,
+}
+
+impl database::Query for GetByHash {
+    type Result = ApiToken;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM api_tokens WHERE token_hash = ?1 AND revoked_at IS NULL"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.token_hash.as_str().into()]
+    }
+}
+
+pub struct GetAllFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for GetAllFromUser {
+    type Result = ApiToken;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM api_tokens WHERE user = ?1 AND revoked_at IS NULL ORDER BY created_at DESC"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+pub struct Touch {
+    pub id: ApiTokenId,
+    pub now: String,
+}
+
+impl database::Query for Touch {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.now.as_str().into(), self.id.into()]
+    }
+}
+
+pub struct Revoke {
+    pub id: ApiTokenId,
+    pub user: UserId,
+    pub now: String,
+}
+
+impl database::Query for Revoke {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE api_tokens SET revoked_at = ?1 WHERE id = ?2 AND user = ?3"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.now.as_str().into(), self.id.into(), self.user.into()]
+    }
+}
+
+pub struct DeleteAllFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteAllFromUser {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM api_tokens WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+impl std::fmt::Display for ApiTokenId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -0,0 +1,382 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database,
+    models::{ticket::TicketId, user::UserId},
+};
+
+/// Something worth recording for later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Login,
+    LoginFailed,
+    Register,
+    TicketAdded,
+    TicketEdited,
+    TicketArchived,
+    TicketUnarchived,
+    TicketDeleted,
+    TicketRestored,
+    TicketPurged,
+    UsageIncremented,
+    UsageDecremented,
+    JourneyEdited,
+    JourneyDeleted,
+    RoleGranted,
+    RoleRevoked,
+    InviteCreated,
+    DefinitionCreated,
+    DefinitionUpdated,
+    DefinitionRetired,
+    DefinitionRestored,
+    DefinitionDeleted,
+    UserDisabled,
+    UserEnabled,
+    PasswordChangeRequired,
+    SessionsRevoked,
+    UserDeleted,
+}
+
+impl Action {
+    pub const ALL: [Action; 27] = [
+        Action::Login,
+        Action::LoginFailed,
+        Action::Register,
+        Action::TicketAdded,
+        Action::TicketEdited,
+        Action::TicketArchived,
+        Action::TicketUnarchived,
+        Action::TicketDeleted,
+        Action::TicketRestored,
+        Action::TicketPurged,
+        Action::UsageIncremented,
+        Action::UsageDecremented,
+        Action::JourneyEdited,
+        Action::JourneyDeleted,
+        Action::RoleGranted,
+        Action::RoleRevoked,
+        Action::InviteCreated,
+        Action::DefinitionCreated,
+        Action::DefinitionUpdated,
+        Action::DefinitionRetired,
+        Action::DefinitionRestored,
+        Action::DefinitionDeleted,
+        Action::UserDisabled,
+        Action::UserEnabled,
+        Action::PasswordChangeRequired,
+        Action::SessionsRevoked,
+        Action::UserDeleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Login => "login",
+            Action::LoginFailed => "login_failed",
+            Action::Register => "register",
+            Action::TicketAdded => "ticket_added",
+            Action::TicketEdited => "ticket_edited",
+            Action::TicketArchived => "ticket_archived",
+            Action::TicketUnarchived => "ticket_unarchived",
+            Action::TicketDeleted => "ticket_deleted",
+            Action::TicketRestored => "ticket_restored",
+            Action::TicketPurged => "ticket_purged",
+            Action::UsageIncremented => "usage_incremented",
+            Action::UsageDecremented => "usage_decremented",
+            Action::JourneyEdited => "journey_edited",
+            Action::JourneyDeleted => "journey_deleted",
+            Action::RoleGranted => "role_granted",
+            Action::RoleRevoked => "role_revoked",
+            Action::InviteCreated => "invite_created",
+            Action::DefinitionCreated => "definition_created",
+            Action::DefinitionUpdated => "definition_updated",
+            Action::DefinitionRetired => "definition_retired",
+            Action::DefinitionRestored => "definition_restored",
+            Action::DefinitionDeleted => "definition_deleted",
+            Action::UserDisabled => "user_disabled",
+            Action::UserEnabled => "user_enabled",
+            Action::PasswordChangeRequired => "password_change_required",
+            Action::SessionsRevoked => "sessions_revoked",
+            Action::UserDeleted => "user_deleted",
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or(())
+    }
+}
+
+impl From<Action> for database::Binding {
+    fn from(val: Action) -> Self {
+        database::Binding::from(val.as_str())
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: u32,
+    /// Who did it, if anyone was signed in.
+    pub actor: Option<UserId>,
+    pub action: Action,
+    pub target_user: Option<UserId>,
+    pub target_ticket: Option<TicketId>,
+    pub detail: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: String,
+}
+
+/// Events are only ever added and never removed; the one change made to them
+/// is [`Forget`] when an account is erased.
+pub struct Insert {
+    pub actor: Option<UserId>,
+    pub action: Action,
+    pub target_user: Option<UserId>,
+    pub target_ticket: Option<TicketId>,
+    pub detail: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: String,
+}
+
+impl database::Query for Insert {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO audit_events (actor, action, target_user, target_ticket, detail, ip, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.actor.into(),
+            self.action.into(),
+            self.target_user.into(),
+            self.target_ticket.into(),
+            self.detail.as_deref().into(),
+            self.ip.as_deref().into(),
+            self.created_at.as_str().into(),
+        ]
+    }
+}
+
+/// Newest first. Each filter is skipped when it's `None`; `user` matches
+/// events done by or to the user.
+pub struct Search {
+    pub action: Option<Action>,
+    pub user: Option<UserId>,
+    pub ticket: Option<TicketId>,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl database::Query for Search {
+    type Result = AuditEvent;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM audit_events
+        WHERE (?1 IS NULL OR action = ?1)
+            AND (?2 IS NULL OR actor = ?2 OR target_user = ?2)
+            AND (?3 IS NULL OR target_ticket = ?3)
+        ORDER BY id DESC
+        LIMIT ?4 OFFSET ?5"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.action.into(),
+            self.user.into(),
+            self.ticket.into(),
+            self.limit.into(),
+            self.offset.into(),
+        ]
+    }
+}
+
+/// Removes an erased user from the log while keeping the events themselves,
+/// so what happened to shared data like definitions, invites and other
+/// accounts stays on record.
+///
+/// Their id is cleared from `actor` and `target_user`, along with the IP
+/// address of anything they did. Details are cleared from events that name
+/// the user: failed logins, which start with the username that was tried,
+/// and admin actions on the account, which carry its username.
+pub struct Forget {
+    pub user: UserId,
+    pub username: String,
+}
+
+impl database::Query for Forget {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE audit_events SET
+            detail = CASE
+                WHEN action IN ('login_failed', 'user_disabled', 'user_enabled',
+                    'password_change_required', 'sessions_revoked', 'user_deleted') THEN NULL
+                ELSE detail
+            END,
+            ip = CASE
+                WHEN actor = ?1 OR actor IS NULL THEN NULL
+                ELSE ip
+            END,
+            actor = NULLIF(actor, ?1),
+            target_user = NULLIF(target_user, ?1)
+        WHERE actor = ?1
+            OR target_user = ?1
+            OR (action = 'login_failed' AND substr(detail, 1, length(?2) + 1) = ?2 || ':')"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.username.as_str().into()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, Connection};
+
+    use super::*;
+    use crate::database::Query;
+
+    const RIDER: UserId = UserId(2);
+    const ADMIN: UserId = UserId(1);
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../../schema.sql"))
+            .unwrap();
+        conn
+    }
+
+    fn record(
+        conn: &Connection,
+        actor: Option<UserId>,
+        action: Action,
+        user: Option<UserId>,
+        detail: &str,
+    ) {
+        conn.execute(
+            "INSERT INTO audit_events (actor, action, target_user, detail, ip, created_at)
+            VALUES (?1, ?2, ?3, ?4, '192.0.2.1', '2025-01-01T08:00:00.000000000')",
+            params![
+                actor.map(|id| id.0),
+                action.as_str(),
+                user.map(|id| id.0),
+                detail
+            ],
+        )
+        .unwrap();
+    }
+
+    /// The `actor`, `target_user`, `detail` and `ip` of each event.
+    type Row = (Option<u32>, Option<u32>, Option<String>, Option<String>);
+
+    fn events(conn: &Connection) -> Vec<Row> {
+        let mut statement = conn
+            .prepare("SELECT actor, target_user, detail, ip FROM audit_events ORDER BY id")
+            .unwrap();
+        statement
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn forget(conn: &Connection) {
+        let forget = Forget {
+            user: RIDER,
+            username: String::from("rider"),
+        };
+        conn.execute(forget.query(), params![forget.user.0, forget.username])
+            .unwrap();
+    }
+
+    #[test]
+    fn forgets_the_user_but_keeps_events() {
+        let conn = database();
+        let ip = || Some(String::from("192.0.2.1"));
+        record(&conn, Some(RIDER), Action::Login, Some(RIDER), "google");
+        record(
+            &conn,
+            None,
+            Action::LoginFailed,
+            Some(RIDER),
+            "rider: wrong password",
+        );
+        record(
+            &conn,
+            None,
+            Action::LoginFailed,
+            None,
+            "rider: no such user",
+        );
+        record(
+            &conn,
+            Some(ADMIN),
+            Action::UserDisabled,
+            Some(RIDER),
+            "rider",
+        );
+        record(
+            &conn,
+            Some(ADMIN),
+            Action::RoleGranted,
+            Some(RIDER),
+            "admin",
+        );
+        record(&conn, Some(RIDER), Action::InviteCreated, None, "ABCD");
+
+        forget(&conn);
+
+        assert_eq!(
+            events(&conn),
+            [
+                (None, None, Some(String::from("google")), None),
+                (None, None, None, None),
+                (None, None, None, None),
+                (Some(ADMIN.0), None, None, ip()),
+                // role names don't identify anyone
+                (Some(ADMIN.0), None, Some(String::from("admin")), ip()),
+                (None, None, Some(String::from("ABCD")), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_other_users_alone() {
+        let conn = database();
+        record(
+            &conn,
+            None,
+            Action::LoginFailed,
+            None,
+            "riders: wrong password",
+        );
+        record(
+            &conn,
+            Some(ADMIN),
+            Action::UserDisabled,
+            Some(UserId(3)),
+            "other",
+        );
+
+        let before = events(&conn);
+        forget(&conn);
+
+        assert_eq!(events(&conn), before);
+    }
+}
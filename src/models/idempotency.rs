@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database,
+    models::{ticket::TicketId, user::UserId},
+};
+
+/// A request that was sent with an `Idempotency-Key`, and what it returned
+/// once it finished.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IdempotencyKey {
+    pub user: UserId,
+    pub key: String,
+    pub ticket: TicketId,
+    /// `None` while the first request with the key is still running.
+    pub response: Option<String>,
+    pub created_at: String,
+}
+
+/// Claims a key for a request. Returns nothing if someone already has it,
+/// so only one of several concurrent requests goes ahead.
+pub struct Claim {
+    pub user: UserId,
+    pub key: String,
+    pub ticket: TicketId,
+    pub created_at: String,
+}
+
+impl database::Query for Claim {
+    type Result = IdempotencyKey;
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO idempotency_keys (user, key, ticket, created_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT DO NOTHING
+        RETURNING *"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.user.into(),
+            self.key.as_str().into(),
+            self.ticket.into(),
+            self.created_at.as_str().into(),
+        ]
+    }
+}
+
+pub struct Get {
+    pub user: UserId,
+    pub key: String,
+}
+
+impl database::Query for Get {
+    type Result = IdempotencyKey;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM idempotency_keys WHERE user = ?1 AND key = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.key.as_str().into()]
+    }
+}
+
+/// Stores the response to replay for later requests with the key.
+pub struct Complete {
+    pub user: UserId,
+    pub key: String,
+    pub response: String,
+}
+
+impl database::Query for Complete {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE idempotency_keys SET response = ?1 WHERE user = ?2 AND key = ?3"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.response.as_str().into(),
+            self.user.into(),
+            self.key.as_str().into(),
+        ]
+    }
+}
+
+/// Gives a key back after its request failed, so a retry can go through.
+pub struct Release {
+    pub user: UserId,
+    pub key: String,
+}
+
+impl database::Query for Release {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM idempotency_keys WHERE user = ?1 AND key = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.key.as_str().into()]
+    }
+}
+
+/// Forgets keys older than `before`.
+pub struct Expire {
+    pub before: String,
+}
+
+impl database::Query for Expire {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM idempotency_keys WHERE created_at < ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.before.as_str().into()]
+    }
+}
+
+pub struct DeleteAllFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteAllFromUser {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM idempotency_keys WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{database, models::user::UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InviteId(pub u32);
+
+impl From<InviteId> for database::Binding {
+    fn from(val: InviteId) -> Self {
+        database::Binding::from(val.0)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Invite {
+    pub id: InviteId,
+    pub code: String,
+    /// `None` allows any number of registrations.
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+    pub expires: Option<String>,
+    pub created_by: UserId,
+    pub created_at: String,
+}
+
+/// An account alongside the invite code it registered with.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Registration {
+    pub user: UserId,
+    pub username: String,
+    pub code: Option<String>,
+}
+
+pub struct Insert {
+    pub code: String,
+    pub max_uses: Option<u32>,
+    pub expires: Option<String>,
+    pub created_by: UserId,
+    pub created_at: String,
+}
+
+impl database::Query for Insert {
+    type Result = Invite;
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO invites (code, max_uses, expires, created_by, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        RETURNING *"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.code.as_str().into(),
+            self.max_uses.into(),
+            self.expires.as_deref().into(),
+            self.created_by.into(),
+            self.created_at.as_str().into(),
+        ]
+    }
+}
+
+pub struct GetAll;
+
+impl database::Query for GetAll {
+    type Result = Invite;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM invites ORDER BY created_at DESC"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+/// Uses up one registration from a code, if it is still valid at `now`.
+///
+/// Done in a single statement so two registrations can't both take the last use.
+pub struct Redeem {
+    pub code: String,
+    pub now: String,
+}
+
+impl database::Query for Redeem {
+    type Result = Invite;
+
+    fn query(&self) -> &'static str {
+        "UPDATE invites SET uses = uses + 1
+        WHERE code = ?1
+            AND (max_uses IS NULL OR uses < max_uses)
+            AND (expires IS NULL OR expires > ?2)
+        RETURNING *"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.code.as_str().into(), self.now.as_str().into()]
+    }
+}
+
+pub struct RecordUse {
+    pub invite: InviteId,
+    pub user: UserId,
+    pub used_at: String,
+}
+
+impl database::Query for RecordUse {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO invite_uses (invite, user, used_at) VALUES (?1, ?2, ?3)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.invite.into(),
+            self.user.into(),
+            self.used_at.as_str().into(),
+        ]
+    }
+}
+
+pub struct GetRegistrations;
+
+impl database::Query for GetRegistrations {
+    type Result = Registration;
+
+    fn query(&self) -> &'static str {
+        "SELECT users.id, users.username, invites.code FROM users
+        LEFT JOIN invite_uses ON invite_uses.user = users.id
+        LEFT JOIN invites ON invites.id = invite_uses.invite
+        ORDER BY users.id"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+/// Forgets which invite a user registered with. The invite's use count is
+/// kept, as the code was still spent.
+pub struct DeleteUse {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteUse {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM invite_uses WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+impl std::fmt::Display for InviteId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
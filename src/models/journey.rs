@@ -0,0 +1,562 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    database,
+    models::{ticket::TicketId, user::UserId},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
+pub struct JourneyId(pub u32);
+
+impl From<JourneyId> for database::Binding {
+    fn from(val: JourneyId) -> Self {
+        database::Binding::from(val.0)
+    }
+}
+
+/// One use of a ticket.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct Journey {
+    pub id: JourneyId,
+    pub ticket: TicketId,
+    pub taken_at: String,
+    pub route: Option<String>,
+    pub stop: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// A journey just logged or removed, with the ticket's usage count straight
+/// after it was.
+///
+/// The count comes from the same statement, so nothing can change it in
+/// between. It's worked out row by row, which for a statement touching
+/// several journeys makes the highest count the final one after logging and
+/// the lowest the final one after removing.
+#[derive(Deserialize)]
+#[serde(from = "CountedRow")]
+pub struct Counted {
+    pub journey: Journey,
+    pub usages: u32,
+}
+
+/// A `RETURNING *` journey row with `usages` on the end, as it comes back
+/// from the database: positionally, in schema order.
+#[derive(Deserialize)]
+struct CountedRow(
+    JourneyId,
+    TicketId,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    u32,
+);
+
+impl From<CountedRow> for Counted {
+    fn from(CountedRow(id, ticket, taken_at, route, stop, notes, usages): CountedRow) -> Self {
+        Counted {
+            journey: Journey {
+                id,
+                ticket,
+                taken_at,
+                route,
+                stop,
+                notes,
+            },
+            usages,
+        }
+    }
+}
+
+impl Counted {
+    /// The usage count once every journey in `logged` is in.
+    pub fn after_logging(logged: &[Counted]) -> Option<u32> {
+        logged.iter().map(|counted| counted.usages).max()
+    }
+
+    /// The usage count once every journey in `removed` is gone.
+    pub fn after_removing(removed: &[Counted]) -> Option<u32> {
+        removed.iter().map(|counted| counted.usages).min()
+    }
+}
+
+pub struct Insert {
+    pub ticket: TicketId,
+    pub taken_at: String,
+    pub route: Option<String>,
+    pub stop: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl database::Query for Insert {
+    type Result = Counted;
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO journeys (ticket, taken_at, route, stop, notes)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        RETURNING *, (SELECT COUNT(*) FROM journeys AS counted WHERE counted.ticket = journeys.ticket) AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.ticket.into(),
+            self.taken_at.as_str().into(),
+            self.route.as_deref().into(),
+            self.stop.as_deref().into(),
+            self.notes.as_deref().into(),
+        ]
+    }
+}
+
+/// Logs `count` journeys at once, all taken at `taken_at`.
+pub struct InsertMany {
+    pub ticket: TicketId,
+    pub count: u32,
+    pub taken_at: String,
+}
+
+impl database::Query for InsertMany {
+    type Result = Counted;
+
+    fn query(&self) -> &'static str {
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?2)
+        INSERT INTO journeys (ticket, taken_at)
+        SELECT ?1, ?3 FROM n
+        RETURNING *, (SELECT COUNT(*) FROM journeys AS counted WHERE counted.ticket = journeys.ticket) AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.ticket.into(),
+            self.count.into(),
+            self.taken_at.as_str().into(),
+        ]
+    }
+}
+
+/// Removes the `count` most recent journeys on a ticket, returning them.
+///
+/// It's all or nothing: when the ticket has fewer than `count` journeys,
+/// none are removed. The check and the delete are one statement, so a
+/// concurrent undo can't slip between them.
+pub struct UndoLatest {
+    pub ticket: TicketId,
+    pub count: u32,
+}
+
+impl database::Query for UndoLatest {
+    type Result = Counted;
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM journeys
+        WHERE id IN (
+            SELECT id FROM journeys WHERE ticket = ?1
+            ORDER BY taken_at DESC, id DESC
+            LIMIT ?2
+        )
+        AND (SELECT COUNT(*) FROM journeys WHERE ticket = ?1) >= ?2
+        RETURNING *, (SELECT COUNT(*) FROM journeys AS counted WHERE counted.ticket = journeys.ticket) AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.ticket.into(), self.count.into()]
+    }
+}
+
+/// A journey on a particular ticket, so one can't be reached through
+/// someone else's.
+pub struct Get {
+    pub id: JourneyId,
+    pub ticket: TicketId,
+}
+
+impl database::Query for Get {
+    type Result = Journey;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM journeys WHERE id = ?1 AND ticket = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into(), self.ticket.into()]
+    }
+}
+
+/// The most recent journey on a ticket.
+pub struct GetLatest {
+    pub ticket: TicketId,
+}
+
+impl database::Query for GetLatest {
+    type Result = Journey;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM journeys WHERE ticket = ?1 ORDER BY taken_at DESC, id DESC LIMIT 1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.ticket.into()]
+    }
+}
+
+/// Undoes the most recent journey on a ticket, but only if it was taken at
+/// or after `since`.
+pub struct UndoRecent {
+    pub ticket: TicketId,
+    pub since: String,
+}
+
+impl database::Query for UndoRecent {
+    type Result = Counted;
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM journeys
+        WHERE id = (
+            SELECT id FROM journeys WHERE ticket = ?1
+            ORDER BY taken_at DESC, id DESC
+            LIMIT 1
+        )
+        AND taken_at >= ?2
+        RETURNING *, (SELECT COUNT(*) FROM journeys AS counted WHERE counted.ticket = journeys.ticket) AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.ticket.into(), self.since.as_str().into()]
+    }
+}
+
+/// Corrects when a journey was taken and what was noted about it.
+pub struct Update {
+    pub id: JourneyId,
+    pub ticket: TicketId,
+    pub taken_at: String,
+    pub notes: Option<String>,
+}
+
+impl database::Query for Update {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE journeys SET taken_at = ?1, notes = ?2 WHERE id = ?3 AND ticket = ?4"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.taken_at.as_str().into(),
+            self.notes.as_deref().into(),
+            self.id.into(),
+            self.ticket.into(),
+        ]
+    }
+}
+
+/// Returns the journey if this removed it, so a double delete is noticed.
+pub struct Delete {
+    pub id: JourneyId,
+    pub ticket: TicketId,
+}
+
+impl database::Query for Delete {
+    type Result = Counted;
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM journeys WHERE id = ?1 AND ticket = ?2
+        RETURNING *, (SELECT COUNT(*) FROM journeys AS counted WHERE counted.ticket = journeys.ticket) AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into(), self.ticket.into()]
+    }
+}
+
+/// A ticket's usage count as the database has it now, after any concurrent
+/// changes.
+pub struct CountFromTicket {
+    pub ticket: TicketId,
+}
+
+impl database::Query for CountFromTicket {
+    type Result = super::Count;
+
+    fn query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM journeys WHERE ticket = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.ticket.into()]
+    }
+}
+
+pub struct GetAllFromTicket {
+    pub ticket: TicketId,
+}
+
+impl database::Query for GetAllFromTicket {
+    type Result = Journey;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM journeys WHERE ticket = ?1 ORDER BY taken_at DESC, id DESC"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.ticket.into()]
+    }
+}
+
+/// One page of a ticket's journeys, most recent first.
+pub struct GetPageFromTicket {
+    pub ticket: TicketId,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl database::Query for GetPageFromTicket {
+    type Result = Journey;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM journeys WHERE ticket = ?1
+        ORDER BY taken_at DESC, id DESC
+        LIMIT ?2 OFFSET ?3"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.ticket.into(), self.limit.into(), self.offset.into()]
+    }
+}
+
+/// Journeys on all of the user's tickets, leaving out deleted ones.
+pub struct GetAllFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for GetAllFromUser {
+    type Result = Journey;
+
+    fn query(&self) -> &'static str {
+        "SELECT journeys.* FROM journeys
+        JOIN user_tickets ON user_tickets.id = journeys.ticket
+        WHERE user_tickets.user = ?1 AND user_tickets.deleted_at IS NULL
+        ORDER BY journeys.taken_at, journeys.id"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+/// Like [`GetAllFromUser`], but with the journeys of deleted tickets too, for
+/// the data export.
+pub struct GetEveryFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for GetEveryFromUser {
+    type Result = Journey;
+
+    fn query(&self) -> &'static str {
+        "SELECT journeys.* FROM journeys
+        JOIN user_tickets ON user_tickets.id = journeys.ticket
+        WHERE user_tickets.user = ?1
+        ORDER BY journeys.taken_at, journeys.id"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+/// Has to run before the user's tickets are deleted, as it finds the
+/// journeys through them.
+pub struct DeleteAllFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteAllFromUser {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM journeys
+        WHERE ticket IN (SELECT id FROM user_tickets WHERE user = ?1)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+/// Has to run before the tickets deleted before `before` are purged, as it
+/// finds the journeys through them.
+pub struct DeleteFromDeletedTickets {
+    pub before: String,
+}
+
+impl database::Query for DeleteFromDeletedTickets {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM journeys
+        WHERE ticket IN (SELECT id FROM user_tickets WHERE deleted_at <= ?1)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.before.as_str().into()]
+    }
+}
+
+impl std::fmt::Display for JourneyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, thread};
+
+    use rusqlite::{params, Connection};
+
+    use super::*;
+    use crate::database::Query;
+
+    const TICKET: TicketId = TicketId(1);
+
+    /// A fresh database on disk, so each thread can open its own
+    /// connection to it like separate requests do.
+    fn database() -> PathBuf {
+        let path = std::env::temp_dir().join(format!("bee-{}.db", uuid::Uuid::new_v4()));
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(include_str!("../../schema.sql"))
+            .unwrap();
+        path
+    }
+
+    fn connect(path: &PathBuf) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.busy_timeout(std::time::Duration::from_secs(30))
+            .unwrap();
+        conn
+    }
+
+    fn count(conn: &Connection) -> u32 {
+        conn.query_row(
+            CountFromTicket { ticket: TICKET }.query(),
+            params![TICKET.0],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    /// The `usages` column of every row a statement returns.
+    fn usages(conn: &Connection, query: &str, params: impl rusqlite::Params) -> Vec<u32> {
+        let mut statement = conn.prepare(query).unwrap();
+        statement
+            .query_map(params, |row| row.get("usages"))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn insert() -> Insert {
+        Insert {
+            ticket: TICKET,
+            taken_at: String::from("2025-01-01T08:00:00.000000000"),
+            route: None,
+            stop: None,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn concurrent_increments_all_count() {
+        const N: usize = 32;
+        let path = database();
+
+        let handles = (0..N)
+            .map(|_| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let conn = connect(&path);
+                    let insert = insert();
+                    usages(
+                        &conn,
+                        insert.query(),
+                        params![
+                            TICKET.0,
+                            insert.taken_at,
+                            None::<String>,
+                            None::<String>,
+                            None::<String>
+                        ],
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut returned = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        returned.sort_unstable();
+
+        assert_eq!(count(&connect(&path)), N as u32);
+        // each increment saw its own count, with nothing lost in between
+        assert_eq!(returned, (1..=N as u32).collect::<Vec<_>>());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn counts_come_back_from_the_change() {
+        let path = database();
+        let conn = connect(&path);
+
+        let many = InsertMany {
+            ticket: TICKET,
+            count: 5,
+            taken_at: String::from("2025-01-01T08:00:00.000000000"),
+        };
+        let logged = usages(
+            &conn,
+            many.query(),
+            params![TICKET.0, many.count, many.taken_at],
+        );
+        assert_eq!(logged.iter().max(), Some(&5));
+
+        let undo = UndoLatest {
+            ticket: TICKET,
+            count: 2,
+        };
+        let undone = usages(&conn, undo.query(), params![TICKET.0, undo.count]);
+        assert_eq!(undone.iter().min(), Some(&3));
+        assert_eq!(count(&conn), 3);
+
+        // all or nothing when there aren't enough to undo
+        let too_many = UndoLatest {
+            ticket: TICKET,
+            count: 4,
+        };
+        assert!(usages(&conn, too_many.query(), params![TICKET.0, too_many.count]).is_empty());
+        assert_eq!(count(&conn), 3);
+
+        drop(conn);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn counted_reads_a_returned_row() {
+        // rows come back as arrays, in the shape D1's `raw()` gives them
+        let counted: Counted = serde_json::from_str(
+            r#"[7,1,"2025-01-01T08:00:00.000000000","42","Piccadilly",null,3]"#,
+        )
+        .unwrap();
+
+        assert_eq!(counted.journey.id, JourneyId(7));
+        assert_eq!(counted.journey.ticket, TicketId(1));
+        assert_eq!(counted.journey.route.as_deref(), Some("42"));
+        assert_eq!(counted.journey.stop.as_deref(), Some("Piccadilly"));
+        assert_eq!(counted.journey.notes, None);
+        assert_eq!(counted.usages, 3);
+    }
+}
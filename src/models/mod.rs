@@ -1,2 +1,140 @@
+pub mod api_token;
+pub mod audit;
+pub mod idempotency;
+pub mod invite;
+pub mod journey;
+pub mod money;
+pub mod stats;
 pub mod ticket;
 pub mod user;
+
+use serde::Deserialize;
+use time::{
+    format_description::BorrowedFormatItem,
+    macros::{format_description, time},
+    Date, Duration, Month, PrimitiveDateTime, UtcDateTime,
+};
+
+/// The result of a `SELECT COUNT(*)` query.
+#[derive(Deserialize)]
+pub struct Count {
+    pub count: u32,
+}
+
+/// The current time in UTC, as stored in the database.
+pub fn now() -> PrimitiveDateTime {
+    let now = UtcDateTime::now();
+    PrimitiveDateTime::new(now.date(), now.time())
+}
+
+/// The current time on the network's clock, which ticket validity is given
+/// in.
+pub fn local_now() -> PrimitiveDateTime {
+    uk_time(now())
+}
+
+/// UK time for a UTC time: GMT, or BST from 1am UTC on the last Sunday in
+/// March until 1am UTC on the last Sunday in October.
+pub fn uk_time(utc: PrimitiveDateTime) -> PrimitiveDateTime {
+    let last_sunday = |month| {
+        let last = Date::from_calendar_date(utc.year(), month, 31).expect("month has 31 days");
+        last - Duration::days(last.weekday().number_days_from_sunday().into())
+    };
+    let bst_starts = PrimitiveDateTime::new(last_sunday(Month::March), time!(1:00));
+    let bst_ends = PrimitiveDateTime::new(last_sunday(Month::October), time!(1:00));
+
+    if bst_starts <= utc && utc < bst_ends {
+        utc + Duration::hours(1)
+    } else {
+        utc
+    }
+}
+
+/// The UTC time for a UK time, the reverse of [`uk_time`]. An hour that
+/// happens twice when the clocks go back is taken as the first, in BST.
+pub fn utc_from_uk(local: PrimitiveDateTime) -> PrimitiveDateTime {
+    let bst = local - Duration::hours(1);
+    if uk_time(bst) == local {
+        bst
+    } else {
+        local
+    }
+}
+
+/// How times are stored in the database. Fixed width, so they compare as
+/// text, and readable back with [`Iso8601::DEFAULT`](time::format_description::well_known::Iso8601::DEFAULT).
+const TIMESTAMP: &[BorrowedFormatItem] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:9]");
+
+/// Formats a time the way the database stores them, so they compare as text.
+pub fn timestamp(time: PrimitiveDateTime) -> String {
+    time.format(TIMESTAMP).expect("time can be formatted")
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{format_description::well_known::Iso8601, macros::datetime};
+
+    use super::*;
+
+    #[test]
+    fn timestamp_round_trips() {
+        let time = datetime!(2025-03-30 00:59:59.123456789);
+        let formatted = timestamp(time);
+
+        assert_eq!(formatted, "2025-03-30T00:59:59.123456789");
+        assert_eq!(
+            PrimitiveDateTime::parse(&formatted, &Iso8601::DEFAULT).unwrap(),
+            time
+        );
+    }
+
+    #[test]
+    fn uk_time_follows_bst() {
+        // BST runs from 1am UTC on 30 March to 1am UTC on 26 October 2025
+        assert_eq!(
+            uk_time(datetime!(2025-03-30 0:59)),
+            datetime!(2025-03-30 0:59)
+        );
+        assert_eq!(
+            uk_time(datetime!(2025-03-30 1:00)),
+            datetime!(2025-03-30 2:00)
+        );
+        assert_eq!(
+            uk_time(datetime!(2025-10-26 0:59)),
+            datetime!(2025-10-26 1:59)
+        );
+        assert_eq!(
+            uk_time(datetime!(2025-10-26 1:00)),
+            datetime!(2025-10-26 1:00)
+        );
+    }
+
+    #[test]
+    fn utc_from_uk_undoes_uk_time() {
+        for utc in [
+            datetime!(2025-01-15 12:00),
+            datetime!(2025-03-30 0:59),
+            datetime!(2025-03-30 1:00),
+            datetime!(2025-07-01 23:30),
+            datetime!(2025-10-26 0:30),
+            datetime!(2025-10-26 1:30),
+        ] {
+            // the repeated hour in October comes back as the BST one
+            let expected = if utc == datetime!(2025-10-26 1:30) {
+                datetime!(2025-10-26 0:30)
+            } else {
+                utc
+            };
+            assert_eq!(utc_from_uk(uk_time(utc)), expected, "{utc}");
+        }
+    }
+
+    #[test]
+    fn timestamps_sort_as_text() {
+        let earlier = timestamp(datetime!(2025-01-09 23:00));
+        let later = timestamp(datetime!(2025-01-10 01:00));
+
+        assert!(earlier < later);
+    }
+}
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::database;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum Currency {
+    #[default]
+    #[serde(rename = "GBP")]
+    Gbp,
+    #[serde(rename = "EUR")]
+    Eur,
+    #[serde(rename = "USD")]
+    Usd,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 3] = [Currency::Gbp, Currency::Eur, Currency::Usd];
+
+    /// The ISO 4217 code, as stored in the database.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Gbp => "GBP",
+            Currency::Eur => "EUR",
+            Currency::Usd => "USD",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Gbp => "£",
+            Currency::Eur => "€",
+            Currency::Usd => "$",
+        }
+    }
+
+    /// How many minor units make up one major unit, e.g. 100 pence to the
+    /// pound.
+    pub fn minor_per_major(&self) -> u64 {
+        match self {
+            Currency::Gbp | Currency::Eur | Currency::Usd => 100,
+        }
+    }
+
+    fn minor_digits(&self) -> usize {
+        match self {
+            Currency::Gbp | Currency::Eur | Currency::Usd => 2,
+        }
+    }
+}
+
+impl From<Currency> for database::Binding {
+    fn from(val: Currency) -> Self {
+        database::Binding::from(val.code())
+    }
+}
+
+/// An amount of money, held in minor units (pence) so it's always exact.
+///
+/// Amounts in different currencies never mix: arithmetic between them
+/// returns `None`, like an overflow does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub struct Money {
+    /// In minor units, e.g. `10500` for £105.00.
+    pub minor: u64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(minor: u64, currency: Currency) -> Self {
+        Money { minor, currency }
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        (self.currency == other.currency)
+            .then(|| self.minor.checked_add(other.minor))
+            .flatten()
+            .map(|minor| Money::new(minor, self.currency))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        (self.currency == other.currency)
+            .then(|| self.minor.checked_sub(other.minor))
+            .flatten()
+            .map(|minor| Money::new(minor, self.currency))
+    }
+
+    pub fn checked_mul(self, times: u64) -> Option<Money> {
+        self.minor
+            .checked_mul(times)
+            .map(|minor| Money::new(minor, self.currency))
+    }
+
+    /// Splits the amount into `parts`, rounding down, e.g. the cost of each
+    /// journey on a ticket.
+    pub fn checked_div(self, parts: u64) -> Option<Money> {
+        self.minor
+            .checked_div(parts)
+            .map(|minor| Money::new(minor, self.currency))
+    }
+
+    /// Parses an amount in major units, like `105`, `105.50` or `1,050`, with
+    /// an optional currency symbol. Negative amounts don't parse.
+    pub fn parse(amount: &str, currency: Currency) -> Option<Money> {
+        let amount = amount.trim();
+        let amount = amount.strip_prefix(currency.symbol()).unwrap_or(amount);
+        let (major, minor) = amount.split_once('.').unwrap_or((amount, ""));
+        let major = ungroup(major)?;
+        let major = major.as_str();
+
+        let digits = currency.minor_digits();
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if major.is_empty() || minor.len() > digits || !all_digits(major) || !all_digits(minor) {
+            return None;
+        }
+
+        // "105.5" is 50 pence, not 5
+        let minor = format!("{minor:0<digits$}").parse::<u64>().ok()?;
+        let major = major.parse::<u64>().ok()?;
+
+        Money::new(major, currency)
+            .checked_mul(currency.minor_per_major())?
+            .checked_add(Money::new(minor, currency))
+    }
+
+    /// The amount without a symbol or grouping, like `1050.00`, as form
+    /// inputs take it.
+    pub fn plain(&self) -> String {
+        let per_major = self.currency.minor_per_major();
+        format!(
+            "{}.{:0digits$}",
+            self.minor / per_major,
+            self.minor % per_major,
+            digits = self.currency.minor_digits()
+        )
+    }
+}
+
+/// Drops the commas from a major amount grouped in thousands, like `1,050`,
+/// rejecting commas anywhere else.
+fn ungroup(major: &str) -> Option<String> {
+    let mut groups = major.split(',');
+    let first = groups.next()?;
+    if major.contains(',') && !(1..=3).contains(&first.len()) {
+        return None;
+    }
+
+    let mut ungrouped = first.to_owned();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        ungrouped.push_str(group);
+    }
+
+    Some(ungrouped)
+}
+
+/// Formats the way UK riders expect, like `£1,050.00`.
+impl std::fmt::Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let per_major = self.currency.minor_per_major();
+        let major = (self.minor / per_major).to_string();
+
+        let mut grouped = String::with_capacity(major.len() + major.len() / 3);
+        for (i, digit) in major.chars().enumerate() {
+            if i > 0 && (major.len() - i).is_multiple_of(3) {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+
+        write!(
+            f,
+            "{}{grouped}.{:0digits$}",
+            self.currency.symbol(),
+            self.minor % per_major,
+            digits = self.currency.minor_digits()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gbp(minor: u64) -> Money {
+        Money::new(minor, Currency::Gbp)
+    }
+
+    fn parse(amount: &str) -> Option<Money> {
+        Money::parse(amount, Currency::Gbp)
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(parse("105"), Some(gbp(10500)));
+        assert_eq!(parse("105.5"), Some(gbp(10550)));
+        assert_eq!(parse(" £105.05 "), Some(gbp(10505)));
+        assert_eq!(parse("0.01"), Some(gbp(1)));
+        assert_eq!(parse("1,050.00"), Some(gbp(105000)));
+        assert_eq!(parse("£1,000,000"), Some(gbp(100000000)));
+    }
+
+    #[test]
+    fn rejects_malformed_amounts() {
+        for amount in ["", ".50", "-1", "1.5.0", "abc", "€5", "1 000"] {
+            assert_eq!(parse(amount), None, "{amount:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_too_many_fraction_digits() {
+        assert_eq!(parse("1.005"), None);
+        assert_eq!(parse("1.50"), Some(gbp(150)));
+    }
+
+    #[test]
+    fn commas_only_group_thousands() {
+        for amount in [
+            "1,0.5", ",,5", ",500", "1,00", "1,0000", "1000,000", "1,000,", "1.000,00",
+        ] {
+            assert_eq!(parse(amount), None, "{amount:?}");
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for minor in [0, 1, 99, 100, 10550, 99999, 100000, 123456789] {
+            let money = gbp(minor);
+            assert_eq!(parse(&money.plain()), Some(money));
+            assert_eq!(parse(&money.to_string()), Some(money));
+        }
+    }
+
+    #[test]
+    fn groups_thousands() {
+        assert_eq!(gbp(99900).to_string(), "£999.00");
+        assert_eq!(gbp(100000).to_string(), "£1,000.00");
+        assert_eq!(gbp(100000000).to_string(), "£1,000,000.00");
+        assert_eq!(gbp(5).to_string(), "£0.05");
+        assert_eq!(gbp(100000).plain(), "1000.00");
+    }
+
+    #[test]
+    fn checked_ops_catch_overflow() {
+        let max = gbp(u64::MAX);
+
+        assert_eq!(max.checked_add(gbp(1)), None);
+        assert_eq!(gbp(0).checked_sub(gbp(1)), None);
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(gbp(1).checked_div(0), None);
+        assert_eq!(parse("184467440737095516.16"), None);
+        assert_eq!(parse("99999999999999999999"), None);
+
+        assert_eq!(gbp(100).checked_add(gbp(50)), Some(gbp(150)));
+        assert_eq!(gbp(100).checked_div(3), Some(gbp(33)));
+    }
+
+    #[test]
+    fn currencies_never_mix() {
+        let euro = Money::new(100, Currency::Eur);
+
+        assert_eq!(gbp(100).checked_add(euro), None);
+        assert_eq!(gbp(100).checked_sub(euro), None);
+    }
+}
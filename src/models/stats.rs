@@ -0,0 +1,200 @@
+use time::PrimitiveDateTime;
+
+use crate::models::{
+    money::{Currency, Money},
+    ticket::Ticket,
+};
+
+/// What a single journey costs without a pass, used when the rider hasn't
+/// set their own: the capped Bee Network adult bus fare.
+pub const DEFAULT_SINGLE_FARE: Money = Money {
+    minor: 200,
+    currency: Currency::Gbp,
+};
+
+/// How well a ticket is paying off.
+pub struct Stats {
+    /// What each journey has cost so far, if there have been any.
+    pub cost_per_journey: Option<Money>,
+    /// How many journeys there'll be by expiry if the rider keeps going at
+    /// the same rate.
+    pub projected_journeys: Option<u64>,
+    pub projected_cost_per_journey: Option<Money>,
+    /// Whole days until the ticket expires, zero once it has.
+    pub days_remaining: i64,
+    /// What the ticket is being compared against.
+    pub single_fare: Money,
+    /// How many single fares the ticket cost, rounded up.
+    pub break_even: Option<u64>,
+    /// Singles that would have cost more than the ticket did so far.
+    pub saved: Option<Money>,
+}
+
+impl Stats {
+    pub fn new(ticket: &Ticket, now: PrimitiveDateTime) -> Self {
+        let usages = u64::from(ticket.usages);
+
+        let total = (ticket.expiry - ticket.start).whole_seconds();
+        let elapsed = (now.clamp(ticket.start, ticket.expiry) - ticket.start).whole_seconds();
+        // nothing to go on until the ticket has been valid for a while
+        let projected_journeys = (elapsed > 0 && total > 0)
+            .then(|| u128::from(usages) * total as u128 / elapsed as u128)
+            .and_then(|projected| u64::try_from(projected).ok());
+
+        let single_fare = ticket
+            .single_fare
+            .filter(|fare| fare.minor > 0)
+            .unwrap_or(DEFAULT_SINGLE_FARE);
+        let comparable = single_fare.currency == ticket.price.currency;
+
+        Stats {
+            cost_per_journey: ticket.price.checked_div(usages),
+            projected_journeys,
+            projected_cost_per_journey: projected_journeys
+                .and_then(|projected| ticket.price.checked_div(projected)),
+            days_remaining: (ticket.expiry - now.max(ticket.start)).whole_days().max(0),
+            single_fare,
+            break_even: comparable.then(|| ticket.price.minor.div_ceil(single_fare.minor)),
+            saved: single_fare
+                .checked_mul(usages)
+                .and_then(|singles| singles.checked_sub(ticket.price))
+                .filter(|saved| saved.minor > 0),
+        }
+    }
+
+    /// Journeys still to take before the ticket beats buying singles.
+    pub fn journeys_to_break_even(&self, usages: u32) -> Option<u64> {
+        self.break_even
+            .map(|break_even| break_even.saturating_sub(usages.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::models::ticket::{DefId, Mode, Passenger, TicketId};
+
+    fn gbp(minor: u64) -> Money {
+        Money::new(minor, Currency::Gbp)
+    }
+
+    /// A £60 ticket valid for 30 days from the start of 2025, against £2
+    /// singles.
+    fn ticket(usages: u32) -> Ticket {
+        Ticket {
+            id: TicketId(1),
+            def: DefId(1),
+            title: String::from("Bee Network 30 Day"),
+            price: gbp(6000),
+            start: datetime!(2025-01-01 0:00),
+            expiry: datetime!(2025-01-31 0:00),
+            mode: Mode::Bus,
+            passenger: Passenger::Adult,
+            qr: String::new(),
+            single_fare: Some(gbp(200)),
+            replaces: None,
+            notes: None,
+            archived_at: None,
+            deleted_at: None,
+            usages,
+        }
+    }
+
+    #[test]
+    fn projects_the_current_rate() {
+        let stats = Stats::new(&ticket(5), datetime!(2025-01-11 0:00));
+
+        assert_eq!(stats.cost_per_journey, Some(gbp(1200)));
+        assert_eq!(stats.projected_journeys, Some(15));
+        assert_eq!(stats.projected_cost_per_journey, Some(gbp(400)));
+        assert_eq!(stats.days_remaining, 20);
+    }
+
+    #[test]
+    fn zero_journeys() {
+        let stats = Stats::new(&ticket(0), datetime!(2025-01-16 0:00));
+
+        assert_eq!(stats.cost_per_journey, None);
+        assert_eq!(stats.projected_journeys, Some(0));
+        assert_eq!(stats.projected_cost_per_journey, None);
+        assert_eq!(stats.saved, None);
+        assert_eq!(stats.journeys_to_break_even(0), Some(30));
+    }
+
+    #[test]
+    fn not_started_yet() {
+        let stats = Stats::new(&ticket(0), datetime!(2024-12-25 0:00));
+
+        assert_eq!(stats.projected_journeys, None);
+        assert_eq!(stats.projected_cost_per_journey, None);
+        // counted from the start, not from now
+        assert_eq!(stats.days_remaining, 30);
+    }
+
+    #[test]
+    fn expired_tickets_are_not_extrapolated() {
+        let stats = Stats::new(&ticket(10), datetime!(2025-03-01 0:00));
+
+        assert_eq!(stats.projected_journeys, Some(10));
+        assert_eq!(stats.projected_cost_per_journey, Some(gbp(600)));
+        assert_eq!(stats.days_remaining, 0);
+    }
+
+    #[test]
+    fn breaks_even_on_an_exact_multiple() {
+        let now = datetime!(2025-01-16 0:00);
+        let stats = Stats::new(&ticket(29), now);
+
+        assert_eq!(stats.break_even, Some(30));
+        assert_eq!(stats.journeys_to_break_even(29), Some(1));
+        assert_eq!(stats.saved, None);
+
+        // level with singles isn't a saving yet
+        assert_eq!(Stats::new(&ticket(30), now).saved, None);
+        assert_eq!(Stats::new(&ticket(31), now).saved, Some(gbp(200)));
+    }
+
+    #[test]
+    fn breaks_even_rounding_up() {
+        let mut ticket = ticket(8);
+        ticket.single_fare = Some(gbp(700));
+        let stats = Stats::new(&ticket, datetime!(2025-01-16 0:00));
+
+        // £60 is 8.57 singles at £7
+        assert_eq!(stats.break_even, Some(9));
+        assert_eq!(stats.journeys_to_break_even(8), Some(1));
+        assert_eq!(stats.journeys_to_break_even(9), Some(0));
+        assert_eq!(stats.saved, None);
+
+        ticket.usages = 9;
+        let stats = Stats::new(&ticket, datetime!(2025-01-16 0:00));
+        assert_eq!(stats.saved, Some(gbp(300)));
+    }
+
+    #[test]
+    fn missing_single_fare_uses_the_default() {
+        let mut ticket = ticket(0);
+        let now = datetime!(2025-01-16 0:00);
+
+        ticket.single_fare = None;
+        assert_eq!(Stats::new(&ticket, now).single_fare, DEFAULT_SINGLE_FARE);
+
+        ticket.single_fare = Some(gbp(0));
+        let stats = Stats::new(&ticket, now);
+        assert_eq!(stats.single_fare, DEFAULT_SINGLE_FARE);
+        assert_eq!(stats.break_even, Some(30));
+    }
+
+    #[test]
+    fn other_currencies_dont_compare() {
+        let mut ticket = ticket(40);
+        ticket.single_fare = Some(Money::new(200, Currency::Eur));
+        let stats = Stats::new(&ticket, datetime!(2025-01-16 0:00));
+
+        assert_eq!(stats.break_even, None);
+        assert_eq!(stats.journeys_to_break_even(40), None);
+        assert_eq!(stats.saved, None);
+    }
+}
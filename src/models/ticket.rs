@@ -1,9 +1,16 @@
 use serde::{Deserialize, Serialize};
-use time::PrimitiveDateTime;
-
-use crate::{database, models::user::UserId};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+use time::{Duration, PrimitiveDateTime};
+use utoipa::ToSchema;
+
+use crate::{
+    database,
+    models::{
+        money::{Currency, Money},
+        user::UserId,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(transparent)]
 pub struct DefId(pub u32);
 
@@ -13,7 +20,7 @@ impl From<DefId> for database::Binding {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(transparent)]
 pub struct TicketId(pub u32);
 
@@ -23,21 +30,193 @@ impl From<TicketId> for database::Binding {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// How the ticket lets you travel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Bus,
+    /// Metrolink.
+    Tram,
+    Train,
+    /// Any Bee Network service.
+    Multi,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 4] = [Mode::Bus, Mode::Tram, Mode::Train, Mode::Multi];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Bus => "bus",
+            Mode::Tram => "tram",
+            Mode::Train => "train",
+            Mode::Multi => "multi",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::Bus => "Bus",
+            Mode::Tram => "Metrolink",
+            Mode::Train => "Train",
+            Mode::Multi => "Multi-modal",
+        }
+    }
+
+    /// The Font Awesome icon for the mode.
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Mode::Bus => "fa-bus-simple",
+            Mode::Tram => "fa-train-tram",
+            Mode::Train => "fa-train",
+            Mode::Multi => "fa-route",
+        }
+    }
+}
+
+impl From<Mode> for database::Binding {
+    fn from(val: Mode) -> Self {
+        database::Binding::from(val.as_str())
+    }
+}
+
+/// Who the ticket is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Passenger {
+    Adult,
+    Student,
+    Child,
+    Concession,
+}
+
+impl Passenger {
+    pub const ALL: [Passenger; 4] = [
+        Passenger::Adult,
+        Passenger::Student,
+        Passenger::Child,
+        Passenger::Concession,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Passenger::Adult => "adult",
+            Passenger::Student => "student",
+            Passenger::Child => "child",
+            Passenger::Concession => "concession",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Passenger::Adult => "Adult",
+            Passenger::Student => "Student",
+            Passenger::Child => "Child",
+            Passenger::Concession => "Concession",
+        }
+    }
+
+    /// The Font Awesome icon for the passenger type.
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Passenger::Adult => "fa-user",
+            Passenger::Student => "fa-user-graduate",
+            Passenger::Child => "fa-child",
+            Passenger::Concession => "fa-id-card",
+        }
+    }
+
+    /// What the passenger has to show alongside the ticket, if anything.
+    pub fn proof(&self) -> Option<&'static str> {
+        match self {
+            Passenger::Adult => None,
+            Passenger::Student => Some("Students must show valid ID on use"),
+            Passenger::Child => Some("Proof of age may be asked for on use"),
+            Passenger::Concession => Some("Show your concessionary pass on use"),
+        }
+    }
+}
+
+impl From<Passenger> for database::Binding {
+    fn from(val: Passenger) -> Self {
+        database::Binding::from(val.as_str())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(from = "TicketDefRow")]
 pub struct TicketDef {
     pub id: DefId,
     pub title: String,
-    pub price: u64,
+    pub price: Money,
     pub start: String,
     pub expiry: String,
+    /// Retired definitions can't be added any more, but tickets already
+    /// holding them keep working.
+    pub retired_at: Option<String>,
+    pub mode: Mode,
+    pub passenger: Passenger,
+}
+
+/// A `ticket_defs` row, which keeps a price's amount and currency in
+/// separate columns.
+#[derive(Deserialize)]
+struct TicketDefRow {
+    id: DefId,
+    title: String,
+    price: u64,
+    currency: Currency,
+    start: String,
+    expiry: String,
+    retired_at: Option<String>,
+    mode: Mode,
+    passenger: Passenger,
+}
+
+impl From<TicketDefRow> for TicketDef {
+    fn from(row: TicketDefRow) -> Self {
+        TicketDef {
+            id: row.id,
+            title: row.title,
+            price: Money::new(row.price, row.currency),
+            start: row.start,
+            expiry: row.expiry,
+            retired_at: row.retired_at,
+            mode: row.mode,
+            passenger: row.passenger,
+        }
+    }
+}
+
+impl TicketDef {
+    pub fn is_retired(&self) -> bool {
+        self.retired_at.is_some()
+    }
 }
 
+/// How long a deleted ticket can be restored for.
+pub const DELETE_GRACE: Duration = Duration::days(7);
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct UserTicket {
     pub id: TicketId,
     pub def: DefId,
     pub user: UserId,
     pub qr: String,
+    /// What the rider would otherwise pay per journey, in the definition's
+    /// minor units, if they've said.
+    pub single_fare: Option<u64>,
+    /// The ticket this one was bought to replace or renew, if any.
+    pub replaces: Option<TicketId>,
+    /// The rider's own notes on the ticket.
+    pub notes: Option<String>,
+    /// When the rider put the ticket away, out of their ticket area.
+    pub archived_at: Option<String>,
+    /// When the rider deleted the ticket. It can be restored until
+    /// [`DELETE_GRACE`] has passed, then it's removed for good.
+    pub deleted_at: Option<String>,
+    /// How many journeys have been logged against the ticket, counted from
+    /// the `journeys` table.
     pub usages: u32,
 }
 
@@ -46,14 +225,45 @@ pub struct Ticket {
     pub id: TicketId,
     pub def: DefId,
     pub title: String,
-    pub price: u64,
+    pub price: Money,
     pub start: PrimitiveDateTime,
     pub expiry: PrimitiveDateTime,
+    pub mode: Mode,
+    pub passenger: Passenger,
     pub qr: String,
+    pub single_fare: Option<Money>,
+    pub replaces: Option<TicketId>,
+    pub notes: Option<String>,
+    pub archived_at: Option<String>,
+    pub deleted_at: Option<String>,
     pub usages: u32,
 }
 
+/// Where a ticket is in its validity window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validity {
+    NotYetValid,
+    Valid,
+    Expired,
+}
+
 impl Ticket {
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    /// `now` is in the network's local time, like the ticket's start and
+    /// expiry.
+    pub fn validity(&self, now: PrimitiveDateTime) -> Validity {
+        if now < self.start {
+            Validity::NotYetValid
+        } else if now >= self.expiry {
+            Validity::Expired
+        } else {
+            Validity::Valid
+        }
+    }
+
     pub fn combine(user_ticket: UserTicket, def: &TicketDef) -> Self {
         assert_eq!(
             user_ticket.def, def.id,
@@ -79,7 +289,16 @@ impl Ticket {
             price: def.price,
             start: start_time,
             expiry: expiry_time,
+            mode: def.mode,
+            passenger: def.passenger,
             qr: user_ticket.qr,
+            single_fare: user_ticket
+                .single_fare
+                .map(|fare| Money::new(fare, def.price.currency)),
+            replaces: user_ticket.replaces,
+            notes: user_ticket.notes,
+            archived_at: user_ticket.archived_at,
+            deleted_at: user_ticket.deleted_at,
             usages: user_ticket.usages,
         }
     }
@@ -93,7 +312,8 @@ impl database::Query for GetTicket {
     type Result = UserTicket;
 
     fn query(&self) -> &'static str {
-        "SELECT * from user_tickets WHERE id = ?1"
+        "SELECT *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages
+        FROM user_tickets WHERE id = ?1 AND deleted_at IS NULL"
     }
 
     fn bindings(&self) -> Vec<database::Binding> {
@@ -109,7 +329,8 @@ impl database::Query for GetAllFromUser {
     type Result = UserTicket;
 
     fn query(&self) -> &'static str {
-        "SELECT * FROM user_tickets WHERE user = ?1"
+        "SELECT *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages
+        FROM user_tickets WHERE user = ?1 AND deleted_at IS NULL"
     }
 
     fn bindings(&self) -> Vec<database::Binding> {
@@ -117,38 +338,280 @@ impl database::Query for GetAllFromUser {
     }
 }
 
-pub struct UpdateUsage {
-    pub id: TicketId,
-    pub usages: u32,
+/// Like [`GetAllFromUser`], but with deleted tickets too, for the data
+/// export.
+pub struct GetEveryFromUser {
+    pub id: UserId,
 }
 
-impl database::Query for UpdateUsage {
-    type Result = ();
+impl database::Query for GetEveryFromUser {
+    type Result = UserTicket;
 
     fn query(&self) -> &'static str {
-        "UPDATE user_tickets SET usages = ?1 WHERE id = ?2"
+        "SELECT *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages
+        FROM user_tickets WHERE user = ?1"
     }
 
     fn bindings(&self) -> Vec<database::Binding> {
-        vec![self.usages.into(), self.id.into()]
+        vec![self.id.into()]
     }
 }
 
+/// One page of a user's tickets, oldest first.
+pub struct GetPageFromUser {
+    pub id: UserId,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl database::Query for GetPageFromUser {
+    type Result = UserTicket;
+
+    fn query(&self) -> &'static str {
+        "SELECT *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages
+        FROM user_tickets WHERE user = ?1 AND deleted_at IS NULL
+        ORDER BY id LIMIT ?2 OFFSET ?3"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into(), self.limit.into(), self.offset.into()]
+    }
+}
+
+pub struct CountFromUser {
+    pub id: UserId,
+}
+
+impl database::Query for CountFromUser {
+    type Result = super::Count;
+
+    fn query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM user_tickets WHERE user = ?1 AND deleted_at IS NULL"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into()]
+    }
+}
+
+/// Returns nothing when `replaces` already has a replacement, checked in the
+/// same statement so two renewals at once can't both go through.
 pub struct Insert {
     pub user: UserId,
     pub def: DefId,
     pub qr: String,
+    pub replaces: Option<TicketId>,
 }
 
 impl database::Query for Insert {
+    type Result = UserTicket;
+
+    fn query(&self) -> &'static str {
+        // a new ticket hasn't been on any journeys
+        "INSERT INTO user_tickets (user, def, qr, replaces)
+        SELECT ?1, ?2, ?3, ?4
+        WHERE ?4 IS NULL
+            OR NOT EXISTS (SELECT 1 FROM user_tickets WHERE replaces = ?4 AND deleted_at IS NULL)
+        RETURNING *, 0 AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.user.into(),
+            self.def.into(),
+            self.qr.as_str().into(),
+            self.replaces.into(),
+        ]
+    }
+}
+
+/// The ticket bought to replace or renew the given one, if there is one.
+pub struct GetReplacement {
+    pub id: TicketId,
+}
+
+impl database::Query for GetReplacement {
+    type Result = UserTicket;
+
+    fn query(&self) -> &'static str {
+        "SELECT *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages
+        FROM user_tickets WHERE replaces = ?1 AND deleted_at IS NULL LIMIT 1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into()]
+    }
+}
+
+/// Sets the single fare a ticket is compared against, or goes back to the
+/// default with `None`.
+pub struct SetSingleFare {
+    pub id: TicketId,
+    pub single_fare: Option<u64>,
+}
+
+impl database::Query for SetSingleFare {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE user_tickets SET single_fare = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.single_fare.into(), self.id.into()]
+    }
+}
+
+/// Corrects a ticket's QR data and notes.
+pub struct Update {
+    pub id: TicketId,
+    pub qr: String,
+    pub notes: Option<String>,
+}
+
+impl database::Query for Update {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE user_tickets SET qr = ?1, notes = ?2 WHERE id = ?3"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.qr.as_str().into(),
+            self.notes.as_deref().into(),
+            self.id.into(),
+        ]
+    }
+}
+
+/// Archives a ticket, or brings it back with `None`.
+pub struct SetArchived {
+    pub id: TicketId,
+    pub archived_at: Option<String>,
+}
+
+impl database::Query for SetArchived {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE user_tickets SET archived_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.archived_at.as_deref().into(), self.id.into()]
+    }
+}
+
+/// Hides a ticket everywhere until it's restored or purged.
+pub struct Delete {
+    pub id: TicketId,
+    pub now: String,
+}
+
+impl database::Query for Delete {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE user_tickets SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.now.as_str().into(), self.id.into()]
+    }
+}
+
+/// Undeletes one of the user's tickets, if it was deleted after `since`.
+pub struct Restore {
+    pub id: TicketId,
+    pub user: UserId,
+    pub since: String,
+}
+
+impl database::Query for Restore {
+    type Result = UserTicket;
+
+    fn query(&self) -> &'static str {
+        "UPDATE user_tickets SET deleted_at = NULL
+        WHERE id = ?1 AND user = ?2 AND deleted_at > ?3
+        RETURNING *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into(), self.user.into(), self.since.as_str().into()]
+    }
+}
+
+/// The user's tickets deleted after `since`, which can still be restored.
+pub struct GetDeletedFromUser {
+    pub user: UserId,
+    pub since: String,
+}
+
+impl database::Query for GetDeletedFromUser {
+    type Result = UserTicket;
+
+    fn query(&self) -> &'static str {
+        "SELECT *, (SELECT COUNT(*) FROM journeys WHERE ticket = user_tickets.id) AS usages
+        FROM user_tickets WHERE user = ?1 AND deleted_at > ?2
+        ORDER BY deleted_at DESC"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.since.as_str().into()]
+    }
+}
+
+/// Forgets which tickets replaced ones about to be purged.
+pub struct UnlinkDeleted {
+    pub before: String,
+}
+
+impl database::Query for UnlinkDeleted {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE user_tickets SET replaces = NULL
+        WHERE replaces IN (SELECT id FROM user_tickets WHERE deleted_at <= ?1)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.before.as_str().into()]
+    }
+}
+
+/// Removes tickets deleted before `before` for good, once their journeys and
+/// links from other tickets have gone, returning what went.
+pub struct PurgeDeleted {
+    pub before: String,
+}
+
+impl database::Query for PurgeDeleted {
+    type Result = UserTicket;
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM user_tickets WHERE deleted_at <= ?1
+        RETURNING *, 0 AS usages"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.before.as_str().into()]
+    }
+}
+
+pub struct DeleteAllFromUser {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteAllFromUser {
     type Result = ();
 
     fn query(&self) -> &'static str {
-        "INSERT INTO user_tickets (user, def, qr) VALUES (?1, ?2, ?3)"
+        "DELETE FROM user_tickets WHERE user = ?1"
     }
 
     fn bindings(&self) -> Vec<database::Binding> {
-        vec![self.user.into(), self.def.into(), self.qr.as_str().into()]
+        vec![self.user.into()]
     }
 }
 
@@ -166,6 +629,177 @@ impl database::Query for GetAllDefinitions {
     }
 }
 
+pub struct GetDefinitionsPage {
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl database::Query for GetDefinitionsPage {
+    type Result = TicketDef;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM ticket_defs ORDER BY id LIMIT ?1 OFFSET ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.limit.into(), self.offset.into()]
+    }
+}
+
+pub struct CountDefinitions;
+
+impl database::Query for CountDefinitions {
+    type Result = super::Count;
+
+    fn query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM ticket_defs"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+pub struct GetDefinition {
+    pub id: DefId,
+}
+
+impl database::Query for GetDefinition {
+    type Result = TicketDef;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM ticket_defs WHERE id = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into()]
+    }
+}
+
+/// How many tickets have been added from a definition.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DefHolders {
+    pub def: DefId,
+    pub tickets: u32,
+}
+
+pub struct GetAllHolders;
+
+impl database::Query for GetAllHolders {
+    type Result = DefHolders;
+
+    fn query(&self) -> &'static str {
+        "SELECT def, COUNT(*) FROM user_tickets GROUP BY def"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+pub struct InsertDefinition {
+    pub title: String,
+    pub price: Money,
+    pub start: String,
+    pub expiry: String,
+    pub mode: Mode,
+    pub passenger: Passenger,
+}
+
+impl database::Query for InsertDefinition {
+    type Result = TicketDef;
+
+    fn query(&self) -> &'static str {
+        "INSERT INTO ticket_defs (title, price, currency, start, expiry, mode, passenger)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        RETURNING *"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.title.as_str().into(),
+            self.price.minor.into(),
+            self.price.currency.into(),
+            self.start.as_str().into(),
+            self.expiry.as_str().into(),
+            self.mode.into(),
+            self.passenger.into(),
+        ]
+    }
+}
+
+pub struct UpdateDefinition {
+    pub id: DefId,
+    pub title: String,
+    pub price: Money,
+    pub start: String,
+    pub expiry: String,
+    pub mode: Mode,
+    pub passenger: Passenger,
+}
+
+impl database::Query for UpdateDefinition {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE ticket_defs
+        SET title = ?1, price = ?2, currency = ?3, start = ?4, expiry = ?5, mode = ?6,
+            passenger = ?7
+        WHERE id = ?8"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.title.as_str().into(),
+            self.price.minor.into(),
+            self.price.currency.into(),
+            self.start.as_str().into(),
+            self.expiry.as_str().into(),
+            self.mode.into(),
+            self.passenger.into(),
+            self.id.into(),
+        ]
+    }
+}
+
+/// Retires a definition, or brings it back when `retired_at` is `None`.
+pub struct RetireDefinition {
+    pub id: DefId,
+    pub retired_at: Option<String>,
+}
+
+impl database::Query for RetireDefinition {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE ticket_defs SET retired_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.retired_at.as_deref().into(), self.id.into()]
+    }
+}
+
+/// Deletes a definition only if no tickets have been added from it,
+/// returning it if it was deleted.
+pub struct DeleteDefinition {
+    pub id: DefId,
+}
+
+impl database::Query for DeleteDefinition {
+    type Result = TicketDef;
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM ticket_defs
+        WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM user_tickets WHERE def = ?1)
+        RETURNING *"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into()]
+    }
+}
+
 impl std::fmt::Display for DefId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
@@ -177,3 +811,92 @@ impl std::fmt::Display for TicketId {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use rusqlite::{params, Connection, OptionalExtension};
+
+    use super::*;
+    use crate::database::Query;
+
+    const OLD: TicketId = TicketId(1);
+
+    /// A database on disk holding one ticket to renew, so each thread can
+    /// open its own connection to it like separate requests do.
+    fn database() -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("bee-{}.db", uuid::Uuid::new_v4()));
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(include_str!("../../schema.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO user_tickets (id, user, def, qr) VALUES (?1, 1, 1, 'old')",
+            params![OLD.0],
+        )
+        .unwrap();
+        path
+    }
+
+    /// Runs [`Insert`] renewing [`OLD`], returning the new ticket's id if it
+    /// went through.
+    fn renew(conn: &Connection) -> Option<u32> {
+        let insert = Insert {
+            user: UserId(1),
+            def: DefId(1),
+            qr: String::from("new"),
+            replaces: Some(OLD),
+        };
+        conn.query_row(
+            insert.query(),
+            params![insert.user.0, insert.def.0, insert.qr, OLD.0],
+            |row| row.get("id"),
+        )
+        .optional()
+        .unwrap()
+    }
+
+    #[test]
+    fn concurrent_renewals_replace_once() {
+        const N: usize = 16;
+        let path = database();
+
+        let renewed = thread::scope(|scope| {
+            let threads = (0..N)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let conn = Connection::open(&path).unwrap();
+                        conn.busy_timeout(std::time::Duration::from_secs(30))
+                            .unwrap();
+                        renew(&conn)
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .filter_map(|thread| thread.join().unwrap())
+                .count()
+        });
+
+        assert_eq!(renewed, 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_deleted_replacement_can_be_replaced_again() {
+        let path = database();
+        let conn = Connection::open(&path).unwrap();
+
+        let first = renew(&conn).unwrap();
+        assert_eq!(renew(&conn), None);
+
+        conn.execute(
+            "UPDATE user_tickets SET deleted_at = '2025-01-01T08:00:00.000000000' WHERE id = ?1",
+            params![first],
+        )
+        .unwrap();
+        assert!(renew(&conn).is_some());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
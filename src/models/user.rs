@@ -17,6 +17,34 @@ pub struct User {
     pub id: UserId,
     pub username: String,
     pub(crate) password_hash: String,
+    // defaulted so that sessions stored before these existed still load
+    #[serde(default)]
+    pub disabled_at: Option<String>,
+    #[serde(default)]
+    pub last_login_at: Option<String>,
+    /// Set when an admin requires a password change; the user has to choose a
+    /// new password at their next login.
+    #[serde(default)]
+    pub password_change_required_at: Option<String>,
+    /// Set when the user has asked for closing a ticket to count as a
+    /// journey, as it used to, rather than tapping to validate.
+    #[serde(default)]
+    pub count_on_close_at: Option<String>,
+}
+
+impl User {
+    /// Accounts created through an identity provider don't have one.
+    pub fn has_password(&self) -> bool {
+        !self.password_hash.is_empty()
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+
+    pub fn counts_on_close(&self) -> bool {
+        self.count_on_close_at.is_some()
+    }
 }
 
 pub struct Get {
@@ -35,19 +63,443 @@ impl database::Query for Get {
     }
 }
 
+/// Returns nothing when the username is taken, which the `UNIQUE` constraint
+/// settles even between registrations at the same moment.
 pub struct Insert {
     pub username: String,
     pub password: String,
 }
 
 impl database::Query for Insert {
-    type Result = ();
+    type Result = User;
 
     fn query(&self) -> &'static str {
-        "INSERT INTO users (username, password_hash) VALUES (?1, ?2)"
+        "INSERT OR IGNORE INTO users (username, password_hash) VALUES (?1, ?2) RETURNING *"
     }
 
     fn bindings(&self) -> Vec<database::Binding> {
         vec![self.username.as_str().into(), self.password.as_str().into()]
     }
 }
+
+/// Finds the user an external identity has been linked to.
+pub struct GetByIdentity {
+    pub provider: String,
+    pub subject: String,
+}
+
+impl database::Query for GetByIdentity {
+    type Result = User;
+
+    fn query(&self) -> &'static str {
+        "SELECT users.* FROM users
+        JOIN user_identities ON user_identities.user = users.id
+        WHERE user_identities.provider = ?1 AND user_identities.subject = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.provider.as_str().into(), self.subject.as_str().into()]
+    }
+}
+
+/// Links an identity to a user, unless it's already linked to someone. Read
+/// it back with [`GetByIdentity`] to see who won a race to link it.
+pub struct LinkIdentity {
+    pub user: UserId,
+    pub provider: String,
+    pub subject: String,
+}
+
+impl database::Query for LinkIdentity {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "INSERT OR IGNORE INTO user_identities (user, provider, subject) VALUES (?1, ?2, ?3)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![
+            self.user.into(),
+            self.provider.as_str().into(),
+            self.subject.as_str().into(),
+        ]
+    }
+}
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Rider,
+    Admin,
+    Inspector,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Rider, Role::Admin, Role::Inspector];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Rider => "rider",
+            Role::Admin => "admin",
+            Role::Inspector => "inspector",
+        }
+    }
+}
+
+impl From<Role> for database::Binding {
+    fn from(val: Role) -> Self {
+        database::Binding::from(val.as_str())
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UserRole {
+    pub user: UserId,
+    pub role: Role,
+}
+
+pub struct HasRole {
+    pub user: UserId,
+    pub role: Role,
+}
+
+impl database::Query for HasRole {
+    type Result = UserRole;
+
+    fn query(&self) -> &'static str {
+        "SELECT user, role FROM user_roles WHERE user = ?1 AND role = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.role.into()]
+    }
+}
+
+/// Every role held by every user.
+pub struct GetAllRoles;
+
+impl database::Query for GetAllRoles {
+    type Result = UserRole;
+
+    fn query(&self) -> &'static str {
+        "SELECT user, role FROM user_roles"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+pub struct GrantRole {
+    pub user: UserId,
+    pub role: Role,
+}
+
+impl database::Query for GrantRole {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "INSERT OR IGNORE INTO user_roles (user, role) VALUES (?1, ?2)"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.role.into()]
+    }
+}
+
+pub struct RevokeRole {
+    pub user: UserId,
+    pub role: Role,
+}
+
+impl database::Query for RevokeRole {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM user_roles WHERE user = ?1 AND role = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into(), self.role.into()]
+    }
+}
+
+pub struct GetAll;
+
+impl database::Query for GetAll {
+    type Result = User;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM users ORDER BY id"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+pub struct GetById {
+    pub id: UserId,
+}
+
+impl database::Query for GetById {
+    type Result = User;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM users WHERE id = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into()]
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub id: u32,
+    pub user: UserId,
+    pub provider: String,
+    pub subject: String,
+}
+
+pub struct GetIdentities {
+    pub user: UserId,
+}
+
+impl database::Query for GetIdentities {
+    type Result = Identity;
+
+    fn query(&self) -> &'static str {
+        "SELECT * FROM user_identities WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+pub struct GetRoles {
+    pub user: UserId,
+}
+
+impl database::Query for GetRoles {
+    type Result = UserRole;
+
+    fn query(&self) -> &'static str {
+        "SELECT user, role FROM user_roles WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+pub struct DeleteIdentities {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteIdentities {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM user_identities WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+pub struct DeleteRoles {
+    pub user: UserId,
+}
+
+impl database::Query for DeleteRoles {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM user_roles WHERE user = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.user.into()]
+    }
+}
+
+pub struct Delete {
+    pub id: UserId,
+}
+
+impl database::Query for Delete {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "DELETE FROM users WHERE id = ?1"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.id.into()]
+    }
+}
+
+pub struct RecordLogin {
+    pub id: UserId,
+    pub now: String,
+}
+
+impl database::Query for RecordLogin {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE users SET last_login_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.now.as_str().into(), self.id.into()]
+    }
+}
+
+/// Disables an account, or enables it again when `disabled_at` is `None`.
+pub struct SetDisabled {
+    pub id: UserId,
+    pub disabled_at: Option<String>,
+}
+
+impl database::Query for SetDisabled {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE users SET disabled_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.disabled_at.as_deref().into(), self.id.into()]
+    }
+}
+
+pub struct SetCountOnClose {
+    pub id: UserId,
+    pub count_on_close_at: Option<String>,
+}
+
+impl database::Query for SetCountOnClose {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE users SET count_on_close_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.count_on_close_at.as_deref().into(), self.id.into()]
+    }
+}
+
+pub struct RequirePasswordChange {
+    pub id: UserId,
+    pub now: String,
+}
+
+impl database::Query for RequirePasswordChange {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE users SET password_change_required_at = ?1 WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.now.as_str().into(), self.id.into()]
+    }
+}
+
+/// Sets a new password, which also satisfies any required change.
+pub struct ChangePassword {
+    pub id: UserId,
+    pub password_hash: String,
+}
+
+impl database::Query for ChangePassword {
+    type Result = ();
+
+    fn query(&self) -> &'static str {
+        "UPDATE users SET password_hash = ?1, password_change_required_at = NULL WHERE id = ?2"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![self.password_hash.as_str().into(), self.id.into()]
+    }
+}
+
+/// A user as listed on the admin console.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UserSummary {
+    pub id: UserId,
+    pub username: String,
+    pub disabled_at: Option<String>,
+    pub last_login_at: Option<String>,
+    pub password_change_required_at: Option<String>,
+    pub tickets: u32,
+}
+
+pub struct GetSummaries;
+
+impl database::Query for GetSummaries {
+    type Result = UserSummary;
+
+    fn query(&self) -> &'static str {
+        "SELECT users.id, users.username, users.disabled_at, users.last_login_at,
+            users.password_change_required_at, COUNT(user_tickets.id)
+        FROM users
+        LEFT JOIN user_tickets ON user_tickets.user = users.id
+        GROUP BY users.id
+        ORDER BY users.id"
+    }
+
+    fn bindings(&self) -> Vec<database::Binding> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, Connection, OptionalExtension};
+
+    use super::*;
+    use crate::database::Query;
+
+    fn insert(conn: &Connection, username: &str) -> Option<u32> {
+        let insert = Insert {
+            username: username.to_owned(),
+            password: String::new(),
+        };
+        conn.query_row(
+            insert.query(),
+            params![insert.username, insert.password],
+            |row| row.get("id"),
+        )
+        .optional()
+        .unwrap()
+    }
+
+    #[test]
+    fn usernames_are_taken_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../../schema.sql"))
+            .unwrap();
+
+        assert!(insert(&conn, "rider").is_some());
+        assert_eq!(insert(&conn, "rider"), None);
+        assert!(insert(&conn, "rider2").is_some());
+    }
+}
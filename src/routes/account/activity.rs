@@ -0,0 +1,23 @@
+use axum::{routing::get, Extension, Router};
+use maud::Markup;
+
+use crate::{auth::AuthUser, markup, models::audit, State};
+
+pub fn router() -> Router {
+    Router::new().route("/", get(activity_page))
+}
+
+async fn activity_page(AuthUser(user): AuthUser, Extension(state): Extension<State>) -> Markup {
+    let events = state
+        .db
+        .query(audit::Search {
+            action: None,
+            user: Some(user.id),
+            ticket: None,
+            limit: 50,
+            offset: 0,
+        })
+        .await;
+
+    markup::page(Some(&user), markup::account::activity(&user, &events))
+}
@@ -0,0 +1,275 @@
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Form, Json, Router,
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use axum_htmx::{HxRedirect, HxReswap, HxRetarget, SwapOption};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{self, AuthUser},
+    config::OidcProvider,
+    error::AppError,
+    markup,
+    models::{
+        self, api_token,
+        audit::{self, AuditEvent},
+        idempotency, invite,
+        journey::{self, Journey},
+        ticket,
+        user::{self, Role, User},
+    },
+    routes::{api::v1, ticket::tickets_from_defs},
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/export", get(export))
+        .route("/delete", post(delete_account))
+}
+
+/// Everything we hold about a user, for them to download.
+#[derive(Serialize)]
+struct Export {
+    exported_at: String,
+    profile: Profile,
+    tickets: Vec<ExportedTicket>,
+    journeys: Vec<ExportedJourney>,
+    api_tokens: Vec<ExportedToken>,
+    activity: Vec<AuditEvent>,
+}
+
+#[derive(Serialize)]
+struct Profile {
+    id: user::UserId,
+    username: String,
+    has_password: bool,
+    last_login_at: Option<String>,
+    roles: Vec<Role>,
+    identities: Vec<ExportedIdentity>,
+}
+
+#[derive(Serialize)]
+struct ExportedIdentity {
+    provider: String,
+    subject: String,
+}
+
+/// Deleted tickets are held until their grace period runs out, so they're
+/// exported too, marked with when they were deleted.
+#[derive(Serialize)]
+struct ExportedTicket {
+    #[serde(flatten)]
+    ticket: v1::Ticket,
+    deleted_at: Option<String>,
+}
+
+/// A journey, marked as deleted along with its ticket.
+#[derive(Serialize)]
+struct ExportedJourney {
+    #[serde(flatten)]
+    journey: Journey,
+    deleted_at: Option<String>,
+}
+
+/// Tokens are listed without their hashes, which are no use to anyone.
+#[derive(Serialize)]
+struct ExportedToken {
+    name: String,
+    scopes: String,
+    created_at: String,
+    last_used_at: Option<String>,
+}
+
+async fn export(
+    AuthUser(user): AuthUser,
+    Extension(state): Extension<State>,
+) -> Result<Response, AppError> {
+    let roles = state.db.query(user::GetRoles { user: user.id }).await;
+    let identities = state.db.query(user::GetIdentities { user: user.id }).await;
+    let tokens = state
+        .db
+        .query(api_token::GetAllFromUser { user: user.id })
+        .await;
+    let activity = state
+        .db
+        .query(audit::Search {
+            action: None,
+            user: Some(user.id),
+            ticket: None,
+            limit: u32::MAX,
+            offset: 0,
+        })
+        .await;
+    let journeys = state
+        .db
+        .query(journey::GetEveryFromUser { user: user.id })
+        .await;
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let user_tickets = state
+        .db
+        .query(ticket::GetEveryFromUser { id: user.id })
+        .await;
+    let tickets = tickets_from_defs(user_tickets, &defs)?;
+    let deleted_at = |journey: &Journey| {
+        tickets
+            .iter()
+            .find(|ticket| ticket.id == journey.ticket)
+            .and_then(|ticket| ticket.deleted_at.clone())
+    };
+
+    let export = Export {
+        exported_at: models::timestamp(models::now()),
+        profile: Profile {
+            id: user.id,
+            username: user.username.clone(),
+            has_password: user.has_password(),
+            last_login_at: user.last_login_at.clone(),
+            roles: roles.into_iter().map(|role| role.role).collect(),
+            identities: identities
+                .into_iter()
+                .map(|identity| ExportedIdentity {
+                    provider: identity.provider,
+                    subject: identity.subject,
+                })
+                .collect(),
+        },
+        journeys: journeys
+            .into_iter()
+            .map(|journey| ExportedJourney {
+                deleted_at: deleted_at(&journey),
+                journey,
+            })
+            .collect(),
+        tickets: tickets
+            .into_iter()
+            .map(|ticket| ExportedTicket {
+                deleted_at: ticket.deleted_at.clone(),
+                ticket: v1::Ticket::from(ticket),
+            })
+            .collect(),
+        api_tokens: tokens
+            .into_iter()
+            .map(|token| ExportedToken {
+                name: token.name,
+                scopes: token.scopes,
+                created_at: token.created_at,
+                last_used_at: token.last_used_at,
+            })
+            .collect(),
+        activity,
+    };
+
+    // named by id, as usernames can hold anything a header can't
+    let disposition = format!("attachment; filename=\"bee-{}.json\"", user.id);
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(export)).into_response())
+}
+
+#[derive(Deserialize)]
+struct DeleteAccount {
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    provider: String,
+}
+
+/// The configured providers the user has an identity with, any of which can
+/// confirm deleting a password-less account.
+pub(crate) async fn deletion_providers(state: &State, user: &User) -> Vec<OidcProvider> {
+    if user.has_password() {
+        return Vec::new();
+    }
+
+    let identities = state.db.query(user::GetIdentities { user: user.id }).await;
+    state
+        .config
+        .oidc_providers
+        .iter()
+        .filter(|provider| {
+            identities
+                .iter()
+                .any(|identity| identity.provider == provider.name)
+        })
+        .cloned()
+        .collect()
+}
+
+async fn delete_account(
+    jar: CookieJar,
+    AuthUser(user): AuthUser,
+    Extension(state): Extension<State>,
+    Form(form): Form<DeleteAccount>,
+) -> Result<Response, AppError> {
+    let providers = deletion_providers(&state, &user).await;
+    let rejected = |error: &str| {
+        (
+            HxRetarget(String::from("#delete-account")),
+            HxReswap(SwapOption::OuterHtml),
+            markup::account::delete_form(&user, &providers, Some(error)),
+        )
+            .into_response()
+    };
+
+    // a stolen session alone shouldn't be enough to wipe the account, so ask
+    // for the password again
+    if user.has_password() {
+        if !auth::verify_password(&user, &form.password) {
+            return Ok(rejected("Incorrect password."));
+        }
+
+        erase_account(&state, &user).await;
+
+        let mut cookie = Cookie::from("session");
+        cookie.set_path("/");
+
+        return Ok((jar.remove(cookie), markup::root(None)).into_response());
+    }
+
+    // password-less accounts sign in with their provider again instead, which
+    // finishes the deletion; the username only guards against slips
+    if form.username != user.username {
+        return Ok(rejected("That isn't your username."));
+    }
+    if !providers
+        .iter()
+        .any(|provider| provider.name == form.provider)
+    {
+        return Ok(rejected("Choose how you sign in."));
+    }
+
+    let (jar, url) = auth::oidc::confirm_deletion(&state, &form.provider, jar).await?;
+    let url = url.as_str().parse().map_err(AppError::internal)?;
+
+    Ok((HxRedirect(url), jar).into_response())
+}
+
+/// Removes the user's rows across the database, strips them from the audit
+/// log and signs them out everywhere.
+pub(crate) async fn erase_account(state: &State, user: &User) {
+    state
+        .db
+        .run(audit::Forget {
+            user: user.id,
+            username: user.username.clone(),
+        })
+        .await;
+
+    let user = user.id;
+    state.db.run(journey::DeleteAllFromUser { user }).await;
+    state.db.run(ticket::DeleteAllFromUser { user }).await;
+    state.db.run(api_token::DeleteAllFromUser { user }).await;
+    state.db.run(idempotency::DeleteAllFromUser { user }).await;
+    state.db.run(user::DeleteIdentities { user }).await;
+    state.db.run(user::DeleteRoles { user }).await;
+    state.db.run(invite::DeleteUse { user }).await;
+    // invites they created as an admin are kept, they now point at nobody
+    state.db.run(user::Delete { id: user }).await;
+
+    state.sessions.remove_all(user).await;
+}
@@ -0,0 +1,63 @@
+pub mod activity;
+pub mod data;
+pub mod tokens;
+
+use axum::{
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use maud::Markup;
+use serde::Deserialize;
+
+use crate::{
+    auth::AuthUser,
+    markup,
+    models::{self, user},
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(account_page))
+        .route("/preferences", post(set_preferences))
+        .nest("/activity", activity::router())
+        .nest("/tokens", tokens::router())
+        .merge(data::router())
+}
+
+async fn account_page(AuthUser(user): AuthUser, Extension(state): Extension<State>) -> Markup {
+    let providers = data::deletion_providers(&state, &user).await;
+
+    markup::page(Some(&user), markup::account::overview(&user, &providers))
+}
+
+#[derive(Deserialize)]
+struct Preferences {
+    count_on_close: Option<String>,
+}
+
+async fn set_preferences(
+    AuthUser(mut user): AuthUser,
+    Extension(state): Extension<State>,
+    Form(form): Form<Preferences>,
+) -> Markup {
+    // keep when it was first turned on rather than bumping it on every save
+    user.count_on_close_at = match form.count_on_close {
+        Some(_) => user
+            .count_on_close_at
+            .or_else(|| Some(models::timestamp(models::now()))),
+        None => None,
+    };
+
+    state
+        .db
+        .run(user::SetCountOnClose {
+            id: user.id,
+            count_on_close_at: user.count_on_close_at.clone(),
+        })
+        .await;
+
+    let providers = data::deletion_providers(&state, &user).await;
+
+    markup::account::overview(&user, &providers)
+}
@@ -0,0 +1,101 @@
+use axum::{extract::Path, routing::delete, routing::get, Extension, Form, Router};
+use maud::Markup;
+use serde::Deserialize;
+
+use crate::{
+    auth::{token, AuthUser},
+    error::AppError,
+    markup,
+    models::{
+        self,
+        api_token::{self, ApiTokenId, Scope},
+        user::User,
+    },
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(tokens_page).post(create_token))
+        .route("/{token}", delete(revoke_token))
+}
+
+async fn tokens_page(AuthUser(user): AuthUser, Extension(state): Extension<State>) -> Markup {
+    markup::page(Some(&user), tokens(&state, &user, None).await)
+}
+
+async fn tokens(state: &State, user: &User, created: Option<&str>) -> Markup {
+    let tokens = state
+        .db
+        .query(api_token::GetAllFromUser { user: user.id })
+        .await;
+
+    markup::account::tokens(&tokens, created)
+}
+
+#[derive(Deserialize)]
+struct CreateToken {
+    name: String,
+    #[serde(rename = "tickets:read")]
+    read: Option<String>,
+    #[serde(rename = "tickets:write")]
+    write: Option<String>,
+}
+
+async fn create_token(
+    AuthUser(user): AuthUser,
+    Extension(state): Extension<State>,
+    Form(form): Form<CreateToken>,
+) -> Result<Markup, AppError> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest(String::from("Give the token a name.")));
+    }
+
+    let scopes = [
+        (Scope::Read, form.read.is_some()),
+        (Scope::Write, form.write.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, checked)| *checked)
+    .map(|(scope, _)| scope.as_str())
+    .collect::<Vec<_>>();
+    if scopes.is_empty() {
+        return Err(AppError::BadRequest(String::from(
+            "Pick at least one scope.",
+        )));
+    }
+
+    let secret = token::generate();
+
+    state
+        .db
+        .run(api_token::Insert {
+            user: user.id,
+            name: name.to_owned(),
+            token_hash: token::hash(&secret),
+            scopes: scopes.join(" "),
+            created_at: models::timestamp(models::now()),
+        })
+        .await;
+
+    // the only time the token itself is ever shown
+    Ok(tokens(&state, &user, Some(&secret)).await)
+}
+
+async fn revoke_token(
+    AuthUser(user): AuthUser,
+    Path(id): Path<ApiTokenId>,
+    Extension(state): Extension<State>,
+) -> Markup {
+    state
+        .db
+        .run(api_token::Revoke {
+            id,
+            user: user.id,
+            now: models::timestamp(models::now()),
+        })
+        .await;
+
+    tokens(&state, &user, None).await
+}
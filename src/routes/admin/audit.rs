@@ -0,0 +1,75 @@
+use axum::{extract::Query, routing::get, Extension, Router};
+use maud::Markup;
+use serde::Deserialize;
+
+use crate::{
+    auth::{role::Admin, RequireRole},
+    markup::{self, admin::AuditFilter},
+    models::{
+        audit,
+        ticket::TicketId,
+        user::{self, UserId},
+    },
+    State,
+};
+
+const PER_PAGE: u32 = 50;
+
+pub fn router() -> Router {
+    Router::new().route("/", get(audit_page))
+}
+
+/// Filters from the query string. They're kept as text, as the form sends
+/// empty values for "any".
+#[derive(Deserialize)]
+struct AuditQuery {
+    #[serde(default)]
+    action: String,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    ticket: String,
+    page: Option<u32>,
+}
+
+async fn audit_page(
+    RequireRole { user, .. }: RequireRole<Admin>,
+    Extension(state): Extension<State>,
+    Query(query): Query<AuditQuery>,
+) -> Markup {
+    let page = query.page.unwrap_or(1).max(1);
+    let action = query.action.parse().ok();
+    let filter_user = query.user.parse().ok().map(UserId);
+    let ticket = query.ticket.parse().ok().map(TicketId);
+
+    // one extra to tell whether there's another page
+    let mut events = state
+        .db
+        .query(audit::Search {
+            action,
+            user: filter_user,
+            ticket,
+            limit: PER_PAGE + 1,
+            offset: (page - 1).saturating_mul(PER_PAGE),
+        })
+        .await;
+    let more = events.len() > PER_PAGE as usize;
+    events.truncate(PER_PAGE as usize);
+
+    let users = state.db.query(user::GetAll).await;
+
+    markup::page(
+        Some(&user),
+        markup::admin::audit(
+            &events,
+            &users,
+            AuditFilter {
+                action,
+                user: filter_user,
+                ticket,
+                page,
+                more,
+            },
+        ),
+    )
+}
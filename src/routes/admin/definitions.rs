@@ -0,0 +1,283 @@
+use axum::{
+    extract::Path,
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use maud::Markup;
+use serde::Deserialize;
+
+use crate::{
+    audit::{Auditor, Event},
+    auth::{role::Admin, RequireRole},
+    error::AppError,
+    markup,
+    models::{
+        self,
+        audit::Action,
+        money::{Currency, Money},
+        ticket::{self, DefId, Mode, Passenger, TicketDef},
+    },
+    routes::parse_datetime,
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(definitions_page).post(create_definition))
+        .route(
+            "/{def}",
+            get(edit_form)
+                .put(update_definition)
+                .delete(delete_definition),
+        )
+        .route(
+            "/{def}/retire",
+            post(retire_definition).delete(restore_definition),
+        )
+        .route("/{def}/clone", post(clone_definition))
+}
+
+async fn definitions_page(
+    RequireRole { user, .. }: RequireRole<Admin>,
+    Extension(state): Extension<State>,
+) -> Markup {
+    markup::page(Some(&user), definitions(&state).await)
+}
+
+async fn definitions(state: &State) -> Markup {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let holders = state.db.query(ticket::GetAllHolders).await;
+
+    markup::admin::definitions(&defs, &holders)
+}
+
+async fn definition(state: &State, id: DefId) -> Result<TicketDef, AppError> {
+    state
+        .db
+        .query_one(ticket::GetDefinition { id })
+        .await
+        .ok_or(AppError::NotFound)
+}
+
+fn event(action: Action, def: &TicketDef) -> Event {
+    Event::new(action).detail(format!("{} ({})", def.title, def.id))
+}
+
+#[derive(Deserialize)]
+struct DefinitionForm {
+    title: String,
+    price: String,
+    currency: Currency,
+    start: String,
+    expiry: String,
+    mode: Mode,
+    passenger: Passenger,
+}
+
+/// A [`DefinitionForm`] that has passed validation.
+struct Definition {
+    title: String,
+    price: Money,
+    start: String,
+    expiry: String,
+    mode: Mode,
+    passenger: Passenger,
+}
+
+impl DefinitionForm {
+    fn validate(self) -> Result<Definition, AppError> {
+        let title = self.title.trim();
+        if title.is_empty() {
+            return Err(AppError::BadRequest(String::from(
+                "Give the ticket a title.",
+            )));
+        }
+
+        let price = Money::parse(&self.price, self.currency).ok_or_else(|| {
+            AppError::BadRequest(String::from("Price must be an amount like 105.00."))
+        })?;
+
+        let start = parse_datetime(&self.start, "Start")?;
+        let expiry = parse_datetime(&self.expiry, "Expiry")?;
+        if start >= expiry {
+            return Err(AppError::BadRequest(String::from(
+                "The ticket must start before it expires.",
+            )));
+        }
+
+        Ok(Definition {
+            title: title.to_owned(),
+            price,
+            start: models::timestamp(start),
+            expiry: models::timestamp(expiry),
+            mode: self.mode,
+            passenger: self.passenger,
+        })
+    }
+}
+
+async fn create_definition(
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(form): Form<DefinitionForm>,
+) -> Result<Markup, AppError> {
+    let Definition {
+        title,
+        price,
+        start,
+        expiry,
+        mode,
+        passenger,
+    } = form.validate()?;
+
+    let def = state
+        .db
+        .query_one(ticket::InsertDefinition {
+            title,
+            price,
+            start,
+            expiry,
+            mode,
+            passenger,
+        })
+        .await
+        .ok_or_else(|| AppError::internal("inserted definition was not returned"))?;
+    auditor.record(event(Action::DefinitionCreated, &def)).await;
+
+    Ok(definitions(&state).await)
+}
+
+async fn edit_form(
+    Path(id): Path<DefId>,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    let def = definition(&state, id).await?;
+
+    Ok(markup::admin::definition_form(Some(&def)))
+}
+
+async fn update_definition(
+    Path(id): Path<DefId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(form): Form<DefinitionForm>,
+) -> Result<Markup, AppError> {
+    let def = definition(&state, id).await?;
+    let Definition {
+        title,
+        price,
+        start,
+        expiry,
+        mode,
+        passenger,
+    } = form.validate()?;
+
+    state
+        .db
+        .run(ticket::UpdateDefinition {
+            id,
+            title,
+            price,
+            start,
+            expiry,
+            mode,
+            passenger,
+        })
+        .await;
+    auditor.record(event(Action::DefinitionUpdated, &def)).await;
+
+    Ok(definitions(&state).await)
+}
+
+async fn retire_definition(
+    Path(id): Path<DefId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let def = definition(&state, id).await?;
+
+    state
+        .db
+        .run(ticket::RetireDefinition {
+            id,
+            retired_at: Some(models::timestamp(models::now())),
+        })
+        .await;
+    auditor.record(event(Action::DefinitionRetired, &def)).await;
+
+    Ok(definitions(&state).await)
+}
+
+async fn restore_definition(
+    Path(id): Path<DefId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let def = definition(&state, id).await?;
+
+    state
+        .db
+        .run(ticket::RetireDefinition {
+            id,
+            retired_at: None,
+        })
+        .await;
+    auditor
+        .record(event(Action::DefinitionRestored, &def))
+        .await;
+
+    Ok(definitions(&state).await)
+}
+
+/// Copies a definition, e.g. for next term, and opens the copy for editing.
+async fn clone_definition(
+    Path(id): Path<DefId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let def = definition(&state, id).await?;
+
+    let copy = state
+        .db
+        .query_one(ticket::InsertDefinition {
+            title: format!("{} (copy)", def.title),
+            price: def.price,
+            start: def.start,
+            expiry: def.expiry,
+            mode: def.mode,
+            passenger: def.passenger,
+        })
+        .await
+        .ok_or_else(|| AppError::internal("inserted definition was not returned"))?;
+    auditor
+        .record(event(Action::DefinitionCreated, &copy).detail(format!(
+            "{} ({}), cloned from {}",
+            copy.title, copy.id, def.id
+        )))
+        .await;
+
+    Ok(markup::admin::definition_form(Some(&copy)))
+}
+
+async fn delete_definition(
+    Path(id): Path<DefId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let def = definition(&state, id).await?;
+
+    // only goes through if nobody holds a ticket from it
+    if state
+        .db
+        .query_one(ticket::DeleteDefinition { id })
+        .await
+        .is_none()
+    {
+        return Err(AppError::Conflict(String::from(
+            "Riders hold tickets from this definition. Retire it instead.",
+        )));
+    }
+    auditor.record(event(Action::DefinitionDeleted, &def)).await;
+
+    Ok(definitions(&state).await)
+}
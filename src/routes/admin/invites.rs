@@ -0,0 +1,91 @@
+use axum::{routing::get, Extension, Form, Router};
+use maud::Markup;
+use serde::Deserialize;
+use time::{
+    macros::{format_description, time},
+    Date, PrimitiveDateTime,
+};
+
+use crate::{
+    audit::{Auditor, Event},
+    auth::{self, role::Admin, RequireRole},
+    error::AppError,
+    markup,
+    models::{self, audit::Action, invite},
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new().route("/", get(invites_page).post(create_invite))
+}
+
+async fn invites_page(
+    RequireRole { user, .. }: RequireRole<Admin>,
+    Extension(state): Extension<State>,
+) -> Markup {
+    markup::page(Some(&user), invites(&state).await)
+}
+
+async fn invites(state: &State) -> Markup {
+    let invites = state.db.query(invite::GetAll).await;
+    let registrations = state.db.query(invite::GetRegistrations).await;
+
+    markup::admin::invites(&invites, &registrations)
+}
+
+#[derive(Deserialize)]
+struct CreateInvite {
+    max_uses: String,
+    expires: String,
+}
+
+async fn create_invite(
+    RequireRole { user, .. }: RequireRole<Admin>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(form): Form<CreateInvite>,
+) -> Result<Markup, AppError> {
+    let max_uses = match form.max_uses.trim() {
+        "" => None,
+        uses => Some(
+            uses.parse::<u32>()
+                .ok()
+                .filter(|&uses| uses > 0)
+                .ok_or_else(|| {
+                    AppError::BadRequest(String::from("Uses must be a whole number above zero."))
+                })?,
+        ),
+    };
+
+    let expires = match form.expires.trim() {
+        "" => None,
+        expires => {
+            let date = Date::parse(expires, format_description!("[year]-[month]-[day]"))
+                .map_err(|_| AppError::BadRequest(String::from("Expiry must be a date.")))?;
+            // valid up to the end of the chosen day
+            Some(models::timestamp(PrimitiveDateTime::new(
+                date,
+                time!(23:59:59),
+            )))
+        }
+    };
+
+    let invite = state
+        .db
+        .query_one(invite::Insert {
+            code: auth::random_token()[..12].to_uppercase(),
+            max_uses,
+            expires,
+            created_by: user.id,
+            created_at: models::timestamp(models::now()),
+        })
+        .await
+        .ok_or_else(|| AppError::internal("inserted invite was not returned"))?;
+    auditor
+        .record(
+            Event::new(Action::InviteCreated).detail(format!("{} ({})", invite.code, invite.id)),
+        )
+        .await;
+
+    Ok(invites(&state).await)
+}
@@ -0,0 +1,19 @@
+pub mod audit;
+pub mod definitions;
+pub mod invites;
+pub mod roles;
+pub mod users;
+
+use axum::{middleware, Router};
+
+use crate::auth::{role::Admin, RequireRole};
+
+pub fn router() -> Router {
+    Router::new()
+        .nest("/audit", audit::router())
+        .nest("/definitions", definitions::router())
+        .nest("/invites", invites::router())
+        .nest("/roles", roles::router())
+        .nest("/users", users::router())
+        .route_layer(middleware::from_extractor::<RequireRole<Admin>>())
+}
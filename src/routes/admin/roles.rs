@@ -0,0 +1,91 @@
+use axum::{
+    extract::Path,
+    routing::{get, post},
+    Extension, Router,
+};
+use maud::Markup;
+
+use crate::{
+    audit::{Auditor, Event},
+    auth::{role::Admin, RequireRole},
+    error::AppError,
+    markup,
+    models::{
+        audit::Action,
+        user::{self, Role, UserId},
+    },
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(roles_page))
+        .route("/{user}/{role}", post(grant_role).delete(revoke_role))
+}
+
+async fn roles_page(
+    RequireRole { user, .. }: RequireRole<Admin>,
+    Extension(state): Extension<State>,
+) -> Markup {
+    markup::page(Some(&user), roles(&state).await)
+}
+
+async fn roles(state: &State) -> Markup {
+    let users = state.db.query(user::GetAll).await;
+    let roles = state.db.query(user::GetAllRoles).await;
+
+    markup::admin::roles(&users, &roles, &state.config.admins)
+}
+
+async fn grant_role(
+    RequireRole { .. }: RequireRole<Admin>,
+    Path((user, role)): Path<(UserId, Role)>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    // granting is INSERT OR IGNORE, which would take any id
+    if state
+        .db
+        .query_one(user::GetById { id: user })
+        .await
+        .is_none()
+    {
+        return Err(AppError::NotFound);
+    }
+
+    state.db.run(user::GrantRole { user, role }).await;
+    auditor
+        .record(
+            Event::new(Action::RoleGranted)
+                .user(user)
+                .detail(role.as_str()),
+        )
+        .await;
+
+    Ok(roles(&state).await)
+}
+
+async fn revoke_role(
+    RequireRole { user: admin, .. }: RequireRole<Admin>,
+    Path((user, role)): Path<(UserId, Role)>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    // stop admins from locking themselves out
+    if user == admin.id && role == Role::Admin {
+        return Err(AppError::Conflict(String::from(
+            "You can't remove your own admin role.",
+        )));
+    }
+
+    state.db.run(user::RevokeRole { user, role }).await;
+    auditor
+        .record(
+            Event::new(Action::RoleRevoked)
+                .user(user)
+                .detail(role.as_str()),
+        )
+        .await;
+
+    Ok(roles(&state).await)
+}
@@ -0,0 +1,166 @@
+use axum::{
+    extract::Path,
+    routing::{delete, get, post},
+    Extension, Router,
+};
+use maud::Markup;
+
+use crate::{
+    audit::{Auditor, Event},
+    auth::{role::Admin, RequireRole},
+    error::AppError,
+    markup,
+    models::{
+        self,
+        audit::Action,
+        user::{self, User, UserId},
+    },
+    routes::account::data::erase_account,
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(users_page))
+        .route("/{user}", delete(delete_user))
+        .route("/{user}/disable", post(disable_user).delete(enable_user))
+        .route("/{user}/password-change", post(require_password_change))
+        .route("/{user}/sessions", delete(revoke_sessions))
+}
+
+async fn users_page(
+    RequireRole { user, .. }: RequireRole<Admin>,
+    Extension(state): Extension<State>,
+) -> Markup {
+    markup::page(Some(&user), users(&state).await)
+}
+
+async fn users(state: &State) -> Markup {
+    let users = state.db.query(user::GetSummaries).await;
+
+    markup::admin::users(&users)
+}
+
+/// The account being acted on, which mustn't be the admin's own: they
+/// could lock themselves out.
+async fn target(state: &State, admin: &User, id: UserId) -> Result<User, AppError> {
+    if id == admin.id {
+        return Err(AppError::Conflict(String::from(
+            "You can't do that to your own account.",
+        )));
+    }
+
+    state
+        .db
+        .query_one(user::GetById { id })
+        .await
+        .ok_or(AppError::NotFound)
+}
+
+fn event(action: Action, target: &User) -> Event {
+    Event::new(action)
+        .user(target.id)
+        .detail(target.username.as_str())
+}
+
+async fn disable_user(
+    RequireRole { user: admin, .. }: RequireRole<Admin>,
+    Path(id): Path<UserId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let user = target(&state, &admin, id).await?;
+
+    state
+        .db
+        .run(user::SetDisabled {
+            id,
+            disabled_at: Some(models::timestamp(models::now())),
+        })
+        .await;
+    state.sessions.remove_all(id).await;
+    auditor.record(event(Action::UserDisabled, &user)).await;
+
+    Ok(users(&state).await)
+}
+
+async fn enable_user(
+    RequireRole { user: admin, .. }: RequireRole<Admin>,
+    Path(id): Path<UserId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let user = target(&state, &admin, id).await?;
+
+    state
+        .db
+        .run(user::SetDisabled {
+            id,
+            disabled_at: None,
+        })
+        .await;
+    auditor.record(event(Action::UserEnabled, &user)).await;
+
+    Ok(users(&state).await)
+}
+
+/// Makes the user choose a new password at their next login. They still
+/// sign in with their current one first, so this isn't a way to lock out
+/// someone who has stolen it; disable the account for that.
+async fn require_password_change(
+    RequireRole { user: admin, .. }: RequireRole<Admin>,
+    Path(id): Path<UserId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let user = target(&state, &admin, id).await?;
+    if !user.has_password() {
+        return Err(AppError::Conflict(String::from(
+            "This account signs in through an identity provider and has no password.",
+        )));
+    }
+
+    state
+        .db
+        .run(user::RequirePasswordChange {
+            id,
+            now: models::timestamp(models::now()),
+        })
+        .await;
+    // sign them out so the change happens at their next login
+    state.sessions.remove_all(id).await;
+    auditor
+        .record(event(Action::PasswordChangeRequired, &user))
+        .await;
+
+    Ok(users(&state).await)
+}
+
+async fn revoke_sessions(
+    RequireRole { user: admin, .. }: RequireRole<Admin>,
+    Path(id): Path<UserId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let user = target(&state, &admin, id).await?;
+
+    state.sessions.remove_all(id).await;
+    auditor.record(event(Action::SessionsRevoked, &user)).await;
+
+    Ok(users(&state).await)
+}
+
+async fn delete_user(
+    RequireRole { user: admin, .. }: RequireRole<Admin>,
+    Path(id): Path<UserId>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let user = target(&state, &admin, id).await?;
+
+    erase_account(&state, &user).await;
+    // recorded without the user, who is now forgotten
+    auditor.record(Event::new(Action::UserDeleted)).await;
+
+    Ok(users(&state).await)
+}
@@ -0,0 +1,126 @@
+pub mod v1;
+
+use axum::{extract::FromRequestParts, http::request::Parts, routing::get, Json, Router};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use utoipa_axum::router::OpenApiRouter;
+
+use crate::{auth::MaybeUser, error::AppError, models::user::User};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Bee Network Tracker API"),
+    servers((url = "/api")),
+    modifiers(&AuthSchemes),
+    security(("token" = []), ("session" = [])),
+)]
+struct ApiDoc;
+
+/// API tokens from the account page, or the browser's session cookie.
+struct AuthSchemes;
+
+impl Modify for AuthSchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("session"))),
+        );
+    }
+}
+
+/// Where the OpenAPI document is served, the one route it doesn't describe.
+const OPENAPI_PATH: &str = "/openapi.json";
+
+/// The API, with its OpenAPI document at [`OPENAPI_PATH`].
+pub fn router() -> Router {
+    let (router, openapi) = OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .nest("/v1", v1::router())
+        .split_for_parts();
+
+    router.route(OPENAPI_PATH, get(|| async move { Json(openapi) }))
+}
+
+/// The user behind an API request, signed in either with a session cookie or
+/// an API token.
+///
+/// Unlike [`crate::auth::AuthUser`] this doesn't redirect to the login page,
+/// scripts get a JSON [`AppError::Unauthorized`] instead.
+pub struct ApiUser(pub User);
+
+impl<S: Send + Sync> FromRequestParts<S> for ApiUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Ok(MaybeUser(user)) = MaybeUser::from_request_parts(parts, state).await;
+
+        user.map(ApiUser).ok_or_else(|| {
+            AppError::Unauthorized(String::from(
+                "Send an API token as `Authorization: Bearer <token>`.",
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The paths registered on `router`. axum has no way to list them, but
+    /// its `Debug` output has each as `RouteId(n): "/path"`, ahead of the
+    /// fallback's own.
+    fn registered_paths(router: &Router) -> Vec<String> {
+        let debug = format!("{router:?}");
+        let (routes, _) = debug
+            .split_once("fallback_router")
+            .expect("Router's Debug output lists its fallback");
+        routes
+            .split("RouteId(")
+            .skip(1)
+            .filter_map(|entry| {
+                let (_, rest) = entry.split_once("): \"")?;
+                let (path, _) = rest.split_once('"')?;
+                Some(path.to_owned())
+            })
+            .collect()
+    }
+
+    fn documented_paths() -> Vec<String> {
+        let (_, openapi) = OpenApiRouter::with_openapi(ApiDoc::openapi())
+            .nest("/v1", v1::router())
+            .split_for_parts();
+        openapi.paths.paths.into_keys().collect()
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let registered = registered_paths(&router());
+        let documented = documented_paths();
+
+        assert!(registered.iter().any(|path| path == OPENAPI_PATH));
+        assert!(
+            registered.len() > 1,
+            "no API routes found in {registered:?}"
+        );
+        for path in registered.iter().filter(|path| *path != OPENAPI_PATH) {
+            assert!(
+                documented.contains(path),
+                "{path} is routed but missing from the OpenAPI document"
+            );
+        }
+    }
+
+    #[test]
+    fn undocumented_routes_are_caught() {
+        let router = router().route("/v1/secret", get(|| async {}));
+
+        assert!(registered_paths(&router).contains(&String::from("/v1/secret")));
+        assert!(!documented_paths().contains(&String::from("/v1/secret")));
+    }
+}
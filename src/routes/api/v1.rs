@@ -0,0 +1,488 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::ApiUser;
+use crate::{
+    audit::Auditor,
+    error::{AppError, ErrorBody},
+    models::{
+        self,
+        audit::Action,
+        journey::{self, Journey},
+        money::Money,
+        ticket::{self, DefId, Mode, Passenger, TicketDef, TicketId, UserTicket},
+        user::User,
+    },
+    routes::ticket::{self as web, tickets_from_defs},
+    State,
+};
+
+/// Add routes through [`routes!`] so they're documented from the handler's
+/// `#[utoipa::path]`. A plain `.route()` is left out of the document, which
+/// the tests in [`super`] catch.
+pub fn router() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(list_tickets, create_ticket))
+        .routes(routes!(get_ticket))
+        .routes(routes!(get_usages, change_usages))
+        .routes(routes!(list_journeys, log_journey))
+        .routes(routes!(list_definitions))
+}
+
+/// A ticket along with the definition it was bought from.
+#[derive(Serialize, ToSchema)]
+pub struct Ticket {
+    pub id: TicketId,
+    pub def: DefId,
+    pub title: String,
+    pub price: Money,
+    pub start: String,
+    pub expiry: String,
+    pub mode: Mode,
+    pub passenger: Passenger,
+    pub qr: String,
+    pub single_fare: Option<Money>,
+    /// The ticket this one replaced or renewed.
+    pub replaces: Option<TicketId>,
+    pub notes: Option<String>,
+    /// When the ticket was archived, if it has been.
+    pub archived_at: Option<String>,
+    pub usages: u32,
+}
+
+impl From<ticket::Ticket> for Ticket {
+    fn from(ticket: ticket::Ticket) -> Self {
+        Ticket {
+            id: ticket.id,
+            def: ticket.def,
+            title: ticket.title,
+            price: ticket.price,
+            start: models::timestamp(ticket.start),
+            expiry: models::timestamp(ticket.expiry),
+            mode: ticket.mode,
+            passenger: ticket.passenger,
+            qr: ticket.qr,
+            single_fare: ticket.single_fare,
+            replaces: ticket.replaces,
+            notes: ticket.notes,
+            archived_at: ticket.archived_at,
+            usages: ticket.usages,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewTicket {
+    pub def: DefId,
+    pub qr: String,
+    /// A ticket of yours that this one replaces or renews.
+    #[serde(default)]
+    pub replaces: Option<TicketId>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Usages {
+    pub usages: u32,
+}
+
+/// Moves the usage count by `delta`, e.g. `1` after a journey or `-1` to undo
+/// the most recent one. At most [`MAX_DELTA`] either way.
+#[derive(Deserialize, ToSchema)]
+pub struct UsageChange {
+    pub delta: i32,
+}
+
+const MAX_DELTA: u32 = 100;
+
+/// A journey to log, with whatever the rider wants to note about it.
+#[derive(Deserialize, ToSchema)]
+pub struct NewJourney {
+    pub route: Option<String>,
+    pub stop: Option<String>,
+    pub notes: Option<String>,
+}
+
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 100;
+
+/// `?page=` and `?per_page=` on list endpoints. Pages count from 1.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Pagination {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl Pagination {
+    fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    fn per_page(&self) -> u32 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    fn offset(&self) -> u32 {
+        (self.page() - 1).saturating_mul(self.per_page())
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total: u32,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, pagination: &Pagination, total: u32) -> Self {
+        Page {
+            items,
+            page: pagination.page(),
+            per_page: pagination.per_page(),
+            total,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/tickets",
+    tag = "tickets",
+    params(Pagination),
+    responses(
+        (status = 200, description = "The signed in user's tickets", body = Page<Ticket>),
+        (status = 401, body = ErrorBody),
+    ),
+)]
+async fn list_tickets(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<Page<Ticket>>, AppError> {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let user_tickets = state
+        .db
+        .query(ticket::GetPageFromUser {
+            id: user.id,
+            limit: pagination.per_page(),
+            offset: pagination.offset(),
+        })
+        .await;
+    let total = state
+        .db
+        .query_one(ticket::CountFromUser { id: user.id })
+        .await
+        .map_or(0, |count| count.count);
+
+    let tickets = tickets_from_defs(user_tickets, &defs)?
+        .into_iter()
+        .map(Ticket::from)
+        .collect();
+
+    Ok(Json(Page::new(tickets, &pagination, total)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tickets",
+    tag = "tickets",
+    request_body = NewTicket,
+    responses(
+        (status = 201, description = "The ticket that was added", body = Ticket),
+        (status = 400, description = "No such definition, or it's retired", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 404, description = "The ticket to replace isn't yours", body = ErrorBody),
+        (status = 409, description = "The ticket to replace has already been replaced", body = ErrorBody),
+    ),
+)]
+async fn create_ticket(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Json(NewTicket { def, qr, replaces }): Json<NewTicket>,
+) -> Result<(StatusCode, Json<Ticket>), AppError> {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    if !defs.iter().any(|d| d.id == def && !d.is_retired()) {
+        return Err(AppError::BadRequest(format!(
+            "Ticket definition {def} doesn't exist or has been retired."
+        )));
+    }
+
+    if let Some(replaces) = replaces {
+        owned_ticket(&state, &user, replaces).await?;
+    }
+
+    let user_ticket = state
+        .db
+        .query_one(ticket::Insert {
+            user: user.id,
+            def,
+            qr,
+            replaces,
+        })
+        .await
+        .ok_or_else(|| match replaces {
+            Some(_) => web::already_replaced(),
+            None => AppError::internal("inserted ticket was not returned"),
+        })?;
+    auditor.record(web::ticket_added(&user_ticket)).await;
+
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok((StatusCode::CREATED, Json(ticket.into())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tickets/{id}",
+    tag = "tickets",
+    params(("id" = TicketId, Path)),
+    responses(
+        (status = 200, body = Ticket),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    ),
+)]
+async fn get_ticket(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    Path(id): Path<TicketId>,
+) -> Result<Json<Ticket>, AppError> {
+    let user_ticket = owned_ticket(&state, &user, id).await?;
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok(Json(ticket.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tickets/{id}/usages",
+    tag = "tickets",
+    params(("id" = TicketId, Path)),
+    responses(
+        (status = 200, body = Usages),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    ),
+)]
+async fn get_usages(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    Path(id): Path<TicketId>,
+) -> Result<Json<Usages>, AppError> {
+    let user_ticket = owned_ticket(&state, &user, id).await?;
+
+    Ok(Json(Usages {
+        usages: user_ticket.usages,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tickets/{id}/usages",
+    tag = "tickets",
+    params(("id" = TicketId, Path)),
+    request_body = UsageChange,
+    responses(
+        (status = 200, description = "The new usage count", body = Usages),
+        (status = 400, description = "The change is too big", body = ErrorBody),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The count would go below zero, or the ticket has expired or isn't valid yet", body = ErrorBody),
+    ),
+)]
+async fn change_usages(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    Path(id): Path<TicketId>,
+    auditor: Auditor,
+    Json(UsageChange { delta }): Json<UsageChange>,
+) -> Result<Json<Usages>, AppError> {
+    let mut user_ticket = owned_ticket(&state, &user, id).await?;
+
+    let count = delta.unsigned_abs();
+    if count > MAX_DELTA {
+        return Err(AppError::BadRequest(format!(
+            "Change the count by at most {MAX_DELTA} at a time."
+        )));
+    }
+
+    // the count comes back from the statement that changed it, so it
+    // includes anything done concurrently and nothing done since
+    let usages = if delta > 0 {
+        web::ensure_valid(&state, &user_ticket).await?;
+        let logged = state
+            .db
+            .query(journey::InsertMany {
+                ticket: user_ticket.id,
+                count,
+                taken_at: models::timestamp(models::now()),
+            })
+            .await;
+        journey::Counted::after_logging(&logged)
+            .ok_or_else(|| AppError::internal("inserted journeys were not returned"))?
+    } else if delta < 0 {
+        // undoes the most recent journeys first, or none if there aren't
+        // enough by the time the statement runs
+        let undone = state
+            .db
+            .query(journey::UndoLatest {
+                ticket: user_ticket.id,
+                count,
+            })
+            .await;
+        journey::Counted::after_removing(&undone).ok_or_else(|| {
+            AppError::Conflict(String::from("That would take the usage count below zero."))
+        })?
+    } else {
+        web::usages(&state, user_ticket.id).await
+    };
+
+    let action = match delta.signum() {
+        1 => Some(Action::UsageIncremented),
+        -1 => Some(Action::UsageDecremented),
+        _ => None,
+    };
+    if let Some(action) = action {
+        user_ticket.usages = usages;
+        let before = usages.saturating_add_signed(-delta);
+        auditor
+            .record(web::usage_event(action, &user_ticket, before))
+            .await;
+    }
+
+    Ok(Json(Usages { usages }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/definitions",
+    tag = "definitions",
+    params(Pagination),
+    responses(
+        (status = 200, description = "Every kind of ticket that can be added", body = Page<TicketDef>),
+        (status = 401, body = ErrorBody),
+    ),
+)]
+async fn list_definitions(
+    _user: ApiUser,
+    Extension(state): Extension<State>,
+    Query(pagination): Query<Pagination>,
+) -> Json<Page<TicketDef>> {
+    let defs = state
+        .db
+        .query(ticket::GetDefinitionsPage {
+            limit: pagination.per_page(),
+            offset: pagination.offset(),
+        })
+        .await;
+    let total = state
+        .db
+        .query_one(ticket::CountDefinitions)
+        .await
+        .map_or(0, |count| count.count);
+
+    Json(Page::new(defs, &pagination, total))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tickets/{id}/journeys",
+    tag = "tickets",
+    params(("id" = TicketId, Path), Pagination),
+    responses(
+        (status = 200, description = "The ticket's journeys, most recent first", body = Page<Journey>),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    ),
+)]
+async fn list_journeys(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    Path(id): Path<TicketId>,
+    Query(pagination): Query<Pagination>,
+) -> Result<Json<Page<Journey>>, AppError> {
+    let user_ticket = owned_ticket(&state, &user, id).await?;
+    let journeys = state
+        .db
+        .query(journey::GetPageFromTicket {
+            ticket: user_ticket.id,
+            limit: pagination.per_page(),
+            offset: pagination.offset(),
+        })
+        .await;
+
+    Ok(Json(Page::new(journeys, &pagination, user_ticket.usages)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tickets/{id}/journeys",
+    tag = "tickets",
+    params(("id" = TicketId, Path)),
+    request_body = NewJourney,
+    responses(
+        (status = 201, description = "The journey that was logged", body = Journey),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "The ticket has expired or isn't valid yet", body = ErrorBody),
+    ),
+)]
+async fn log_journey(
+    ApiUser(user): ApiUser,
+    Extension(state): Extension<State>,
+    Path(id): Path<TicketId>,
+    auditor: Auditor,
+    Json(NewJourney { route, stop, notes }): Json<NewJourney>,
+) -> Result<(StatusCode, Json<Journey>), AppError> {
+    let mut user_ticket = owned_ticket(&state, &user, id).await?;
+    web::ensure_valid(&state, &user_ticket).await?;
+
+    // blank fields from a form are as good as missing
+    let given = |field: Option<String>| field.filter(|field| !field.trim().is_empty());
+    let logged = state
+        .db
+        .query_one(journey::Insert {
+            ticket: user_ticket.id,
+            taken_at: models::timestamp(models::now()),
+            route: given(route),
+            stop: given(stop),
+            notes: given(notes),
+        })
+        .await
+        .ok_or_else(|| AppError::internal("inserted journey was not returned"))?;
+
+    user_ticket.usages = logged.usages;
+    auditor
+        .record(web::usage_event(
+            Action::UsageIncremented,
+            &user_ticket,
+            user_ticket.usages.saturating_sub(1),
+        ))
+        .await;
+
+    Ok((StatusCode::CREATED, Json(logged.journey)))
+}
+
+/// Someone else's ticket is reported as missing, like
+/// [`crate::routes::ticket::OwnedTicket`] does.
+async fn owned_ticket(state: &State, user: &User, id: TicketId) -> Result<UserTicket, AppError> {
+    state
+        .db
+        .query_one(ticket::GetTicket { id })
+        .await
+        .filter(|ticket| ticket.user == user.id)
+        .ok_or(AppError::NotFound)
+}
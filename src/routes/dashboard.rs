@@ -0,0 +1,34 @@
+use axum::{routing::get, Extension, Router};
+use maud::Markup;
+
+use crate::{
+    auth::AuthUser,
+    error::AppError,
+    markup,
+    models::{self, journey, ticket},
+    routes::ticket::tickets_from_defs,
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new().route("/", get(dashboard_page))
+}
+
+async fn dashboard_page(
+    AuthUser(user): AuthUser,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let user_tickets = state.db.query(ticket::GetAllFromUser { id: user.id }).await;
+    let journeys = state
+        .db
+        .query(journey::GetAllFromUser { user: user.id })
+        .await;
+
+    let tickets = tickets_from_defs(user_tickets, &defs)?;
+
+    Ok(markup::page(
+        Some(&user),
+        markup::dashboard::dashboard(&tickets, &journeys, models::local_now()),
+    ))
+}
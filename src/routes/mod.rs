@@ -1,11 +1,24 @@
+pub mod account;
+pub mod admin;
+pub mod api;
+pub mod dashboard;
 pub mod qr;
 pub mod ticket;
 
-use axum::Extension;
 use maud::Markup;
+use time::{macros::format_description, PrimitiveDateTime};
 
-use crate::{markup, models::user::User};
+use crate::{auth::MaybeUser, error::AppError, markup};
 
-pub async fn index(Extension(user): Extension<Option<User>>) -> Markup {
+pub async fn index(MaybeUser(user): MaybeUser) -> Markup {
     markup::root(user)
 }
+
+/// Parses the value of a `datetime-local` input.
+pub(crate) fn parse_datetime(value: &str, field: &str) -> Result<PrimitiveDateTime, AppError> {
+    PrimitiveDateTime::parse(
+        value.trim(),
+        format_description!("[year]-[month]-[day]T[hour]:[minute]"),
+    )
+    .map_err(|_| AppError::BadRequest(format!("{field} must be a date and time.")))
+}
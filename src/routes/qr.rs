@@ -1,51 +1,22 @@
 use axum::{
-    extract::Query,
     http::{header, StatusCode},
     response::{IntoResponse, Response},
     routing::get,
-    Extension, Router,
+    Router,
 };
 use fast_qr::{
     convert::{svg::SvgBuilder, Builder, Shape},
     Mask, QRBuilder, ECL,
 };
-use serde::Deserialize;
 
-use crate::{
-    models::{
-        ticket::{self, TicketId},
-        user::User,
-    },
-    State,
-};
+use super::ticket::OwnedTicket;
 
 pub fn router() -> Router {
     Router::new().route("/", get(get_qr_svg))
 }
 
-#[derive(Deserialize)]
-struct QrData {
-    ticket: TicketId,
-}
-
 /// Dyanmically creates a QR code in the proper format for the data for the bus app.
-async fn get_qr_svg(
-    Query(QrData { ticket }): Query<QrData>,
-    Extension(user): Extension<Option<User>>,
-    Extension(state): Extension<State>,
-) -> Response {
-    let Some(user) = user else {
-        return StatusCode::UNAUTHORIZED.into_response();
-    };
-
-    let Some(ticket) = state.db.query_one(ticket::GetTicket { id: ticket }).await else {
-        return StatusCode::BAD_REQUEST.into_response();
-    };
-
-    if ticket.user != user.id {
-        return StatusCode::UNAUTHORIZED.into_response();
-    }
-
+async fn get_qr_svg(OwnedTicket(ticket): OwnedTicket) -> Response {
     let qrcode = QRBuilder::new(ticket.qr.as_bytes())
         .ecl(ECL::M)
         .mask(Mask::Diamonds)
@@ -0,0 +1,222 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Extension, Form, Router,
+};
+use axum_htmx::{HxReswap, HxRetarget, SwapOption};
+use maud::Markup;
+use serde::Deserialize;
+
+use super::{tickets_from_defs, OwnedTicket};
+use crate::{
+    audit::{Auditor, Event},
+    error::AppError,
+    markup,
+    models::{
+        self,
+        audit::Action,
+        journey::{self, Journey, JourneyId},
+        money::Money,
+        ticket::{self, UserTicket},
+    },
+    routes::parse_datetime,
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/{ticket}/history", get(history_page))
+        .route("/{ticket}/fare", put(set_single_fare))
+        .route(
+            "/{ticket}/journeys/{journey}",
+            get(edit_form).put(update_journey).delete(delete_journey),
+        )
+}
+
+#[derive(Deserialize)]
+struct JourneyParam {
+    journey: JourneyId,
+}
+
+async fn history_page(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    history(&state, user_ticket, None).await
+}
+
+/// Every journey on the ticket, swapped into `#ticket-area`.
+pub(super) async fn history(
+    state: &State,
+    user_ticket: UserTicket,
+    fare_error: Option<&str>,
+) -> Result<Markup, AppError> {
+    let journeys = state
+        .db
+        .query(journey::GetAllFromTicket {
+            ticket: user_ticket.id,
+        })
+        .await;
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok(markup::history(&ticket, &journeys, fare_error))
+}
+
+async fn journey(
+    state: &State,
+    user_ticket: &UserTicket,
+    id: JourneyId,
+) -> Result<Journey, AppError> {
+    state
+        .db
+        .query_one(journey::Get {
+            id,
+            ticket: user_ticket.id,
+        })
+        .await
+        .ok_or(AppError::NotFound)
+}
+
+fn event(action: Action, user_ticket: &UserTicket, journey: &Journey) -> Event {
+    Event::new(action)
+        .user(user_ticket.user)
+        .ticket(user_ticket.id)
+        .detail(format!("journey {} at {}", journey.id, journey.taken_at))
+}
+
+async fn edit_form(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Path(JourneyParam { journey: id }): Path<JourneyParam>,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    let journey = journey(&state, &user_ticket, id).await?;
+
+    Ok(markup::journey_form(&journey, None))
+}
+
+#[derive(Deserialize)]
+struct JourneyForm {
+    taken_at: String,
+    notes: String,
+}
+
+async fn update_journey(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Path(JourneyParam { journey: id }): Path<JourneyParam>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(form): Form<JourneyForm>,
+) -> Result<Response, AppError> {
+    let mut journey = journey(&state, &user_ticket, id).await?;
+
+    let taken_at = match parse_datetime(&form.taken_at, "Time") {
+        Ok(taken_at) => taken_at,
+        Err(AppError::BadRequest(error)) => {
+            return Ok((
+                HxRetarget(format!("#journey-{id}")),
+                HxReswap(SwapOption::OuterHtml),
+                markup::journey_form(&journey, Some(&error)),
+            )
+                .into_response());
+        }
+        Err(error) => return Err(error),
+    };
+    let notes = form.notes.trim();
+
+    // the form is in UK time, like the history it came from
+    journey.taken_at = models::timestamp(models::utc_from_uk(taken_at));
+    journey.notes = (!notes.is_empty()).then(|| notes.to_owned());
+    state
+        .db
+        .run(journey::Update {
+            id,
+            ticket: user_ticket.id,
+            taken_at: journey.taken_at.clone(),
+            notes: journey.notes.clone(),
+        })
+        .await;
+    auditor
+        .record(event(Action::JourneyEdited, &user_ticket, &journey))
+        .await;
+
+    Ok(history(&state, user_ticket, None).await?.into_response())
+}
+
+/// Removes a journey logged by mistake. The usage count follows.
+async fn delete_journey(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Path(JourneyParam { journey: id }): Path<JourneyParam>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    // gone already if a second tap got here first
+    let deleted = state
+        .db
+        .query_one(journey::Delete {
+            id,
+            ticket: user_ticket.id,
+        })
+        .await
+        .ok_or(AppError::NotFound)?;
+    auditor
+        .record(event(
+            Action::JourneyDeleted,
+            &user_ticket,
+            &deleted.journey,
+        ))
+        .await;
+
+    user_ticket.usages = deleted.usages;
+    history(&state, user_ticket, None).await
+}
+
+#[derive(Deserialize)]
+struct FareForm {
+    single_fare: String,
+}
+
+/// Sets what the ticket's value is judged against. Left blank, it goes back
+/// to the default.
+async fn set_single_fare(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    Form(form): Form<FareForm>,
+) -> Result<Markup, AppError> {
+    let def = state
+        .db
+        .query_one(ticket::GetDefinition {
+            id: user_ticket.def,
+        })
+        .await
+        .ok_or_else(|| AppError::internal("ticket refers to a missing definition"))?;
+
+    let single_fare = form.single_fare.trim();
+    let single_fare = if single_fare.is_empty() {
+        None
+    } else {
+        match Money::parse(single_fare, def.price.currency) {
+            Some(fare) if fare.minor > 0 => Some(fare.minor),
+            _ => {
+                return history(
+                    &state,
+                    user_ticket,
+                    Some("The fare must be an amount like 2.00."),
+                )
+                .await;
+            }
+        }
+    };
+
+    state
+        .db
+        .run(ticket::SetSingleFare {
+            id: user_ticket.id,
+            single_fare,
+        })
+        .await;
+    user_ticket.single_fare = single_fare;
+
+    history(&state, user_ticket, None).await
+}
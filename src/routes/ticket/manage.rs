@@ -0,0 +1,177 @@
+use axum::{
+    extract::Path,
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use maud::Markup;
+use serde::Deserialize;
+
+use super::{history::history, ticket_area, tickets_from_defs, OwnedTicket, TicketParam};
+use crate::{
+    audit::{Auditor, Event},
+    auth::AuthUser,
+    error::AppError,
+    markup,
+    models::{
+        self,
+        audit::Action,
+        ticket::{self, UserTicket},
+    },
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/{ticket}/edit", get(edit_form).put(update_ticket))
+        .route("/{ticket}/archive", post(archive).delete(unarchive))
+        .route("/{ticket}/delete", post(delete_ticket))
+        .route("/{ticket}/restore", post(restore))
+}
+
+fn event(action: Action, user_ticket: &UserTicket) -> Event {
+    Event::new(action)
+        .user(user_ticket.user)
+        .ticket(user_ticket.id)
+}
+
+async fn edit_form(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok(markup::ticket_edit_form(&ticket, None))
+}
+
+#[derive(Deserialize)]
+struct EditTicket {
+    qr: String,
+    notes: String,
+}
+
+/// Corrects the QR data, e.g. after a typo, and the rider's notes.
+async fn update_ticket(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(form): Form<EditTicket>,
+) -> Result<Markup, AppError> {
+    let qr = form.qr.trim();
+    if qr.is_empty() {
+        let defs = state.db.query(ticket::GetAllDefinitions).await;
+        let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+        return Ok(markup::ticket_edit_form(
+            &ticket,
+            Some("The QR data can't be empty."),
+        ));
+    }
+    let notes = form.notes.trim();
+
+    user_ticket.qr = qr.to_owned();
+    user_ticket.notes = (!notes.is_empty()).then(|| notes.to_owned());
+    state
+        .db
+        .run(ticket::Update {
+            id: user_ticket.id,
+            qr: user_ticket.qr.clone(),
+            notes: user_ticket.notes.clone(),
+        })
+        .await;
+    auditor
+        .record(event(Action::TicketEdited, &user_ticket))
+        .await;
+
+    history(&state, user_ticket, None).await
+}
+
+/// Puts a ticket away out of the ticket area, keeping its history.
+async fn archive(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    // archiving again keeps the original time
+    if user_ticket.archived_at.is_none() {
+        user_ticket.archived_at = Some(models::timestamp(models::now()));
+        state
+            .db
+            .run(ticket::SetArchived {
+                id: user_ticket.id,
+                archived_at: user_ticket.archived_at.clone(),
+            })
+            .await;
+        auditor
+            .record(event(Action::TicketArchived, &user_ticket))
+            .await;
+    }
+
+    history(&state, user_ticket, None).await
+}
+
+async fn unarchive(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    if user_ticket.archived_at.is_some() {
+        user_ticket.archived_at = None;
+        state
+            .db
+            .run(ticket::SetArchived {
+                id: user_ticket.id,
+                archived_at: None,
+            })
+            .await;
+        auditor
+            .record(event(Action::TicketUnarchived, &user_ticket))
+            .await;
+    }
+
+    history(&state, user_ticket, None).await
+}
+
+/// Hides the ticket and its journeys. It can be restored from the ticket
+/// area until [`ticket::DELETE_GRACE`] has passed.
+async fn delete_ticket(
+    AuthUser(user): AuthUser,
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    state
+        .db
+        .run(ticket::Delete {
+            id: user_ticket.id,
+            now: models::timestamp(models::now()),
+        })
+        .await;
+    auditor
+        .record(event(Action::TicketDeleted, &user_ticket))
+        .await;
+
+    ticket_area(&state, user.id).await
+}
+
+async fn restore(
+    AuthUser(user): AuthUser,
+    Path(TicketParam { ticket: id }): Path<TicketParam>,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    // someone else's ticket, or one past its grace period, is just missing
+    let user_ticket = state
+        .db
+        .query_one(ticket::Restore {
+            id,
+            user: user.id,
+            since: models::timestamp(models::now() - ticket::DELETE_GRACE),
+        })
+        .await
+        .ok_or(AppError::NotFound)?;
+    auditor
+        .record(event(Action::TicketRestored, &user_ticket))
+        .await;
+
+    ticket_area(&state, user.id).await
+}
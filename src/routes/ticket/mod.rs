@@ -0,0 +1,495 @@
+use axum::{
+    extract::{FromRequestParts, Path, Query},
+    http::{request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Extension, Form, Router,
+};
+use maud::Markup;
+use serde::Deserialize;
+
+mod history;
+mod manage;
+mod validate;
+
+use crate::{
+    audit::{Auditor, Event},
+    auth::AuthUser,
+    error::AppError,
+    markup,
+    models::{
+        self,
+        audit::Action,
+        idempotency, journey,
+        ticket::{self, DefId, Ticket, TicketDef, TicketId, UserTicket, Validity},
+        user::UserId,
+    },
+    State,
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(get_ticket_area))
+        .route("/add", get(ticket_form).post(add_ticket))
+        .route("/{ticket}", get(get_single_ticket))
+        .route("/{ticket}/renew", get(renew_form).post(renew_ticket))
+        .route("/{ticket}/inc", post(increment_usage))
+        .route("/{ticket}/dec", post(decrement_usage))
+        .merge(history::router())
+        .merge(manage::router())
+        .merge(validate::router())
+}
+
+/// A ticket owned by the signed in user, taken from the `{ticket}` path
+/// segment or the `?ticket=` query.
+///
+/// Someone else's ticket is rejected as [`AppError::NotFound`] just like a
+/// missing one, so ticket ids can't be probed.
+pub struct OwnedTicket(pub UserTicket);
+
+#[derive(Deserialize)]
+struct TicketParam {
+    ticket: TicketId,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for OwnedTicket {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let id = match Path::<TicketParam>::from_request_parts(parts, state).await {
+            Ok(Path(TicketParam { ticket })) => ticket,
+            Err(_) => {
+                let Query(TicketParam { ticket }) = Query::<TicketParam>::try_from_uri(&parts.uri)
+                    .map_err(IntoResponse::into_response)?;
+                ticket
+            }
+        };
+
+        let state = State::from_extensions(&parts.extensions);
+        let Some(ticket) = state
+            .db
+            .query_one(ticket::GetTicket { id })
+            .await
+            .filter(|ticket| ticket.user == user.id)
+        else {
+            return Err(AppError::NotFound.into_response());
+        };
+
+        Ok(OwnedTicket(ticket))
+    }
+}
+
+async fn ticket_form(_: AuthUser, Extension(state): Extension<State>) -> Response {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+
+    match markup::ticket_form(&defs) {
+        Some(form) => form.into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn get_ticket_area(
+    AuthUser(user): AuthUser,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    ticket_area(&state, user.id).await
+}
+
+/// The user's tickets, along with any deleted ones they can still restore.
+async fn ticket_area(state: &State, user: UserId) -> Result<Markup, AppError> {
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let user_tickets = state.db.query(ticket::GetAllFromUser { id: user }).await;
+    let deleted = state
+        .db
+        .query(ticket::GetDeletedFromUser {
+            user,
+            since: models::timestamp(models::now() - ticket::DELETE_GRACE),
+        })
+        .await;
+
+    let tickets = tickets_from_defs(user_tickets, &defs)?;
+    let deleted = tickets_from_defs(deleted, &defs)?;
+
+    Ok(markup::ticket_area(&tickets, &deleted))
+}
+
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Set on a response that was replayed for a repeated `Idempotency-Key`.
+const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+/// How long a key is remembered for.
+const IDEMPOTENCY_WINDOW: time::Duration = time::Duration::hours(24);
+
+/// The request's `Idempotency-Key`, if it sent one.
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, AppError> {
+    let Some(key) = headers.get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+
+    match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= 255 => Ok(Some(key.to_owned())),
+        _ => Err(AppError::BadRequest(String::from(
+            "Idempotency-Key must be 1 to 255 visible ASCII characters.",
+        ))),
+    }
+}
+
+/// Logs a journey on the ticket and returns the new count.
+#[axum::debug_handler]
+async fn increment_usage(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    Ok(
+        match log_once(&state, &auditor, &headers, user_ticket).await? {
+            Logged::Now(usages) => usages.into_response(),
+            Logged::Before(usages) => ([(IDEMPOTENT_REPLAYED, "true")], usages).into_response(),
+        },
+    )
+}
+
+/// The usage count after [`log_once`].
+enum Logged {
+    Now(String),
+    /// An earlier request with the same `Idempotency-Key` logged it.
+    Before(String),
+}
+
+/// Logs a journey, unless the request has an `Idempotency-Key` that was
+/// already used within the last day. Then the first request's response is
+/// given back, so retries and double clicks only count once.
+async fn log_once(
+    state: &State,
+    auditor: &Auditor,
+    headers: &HeaderMap,
+    user_ticket: UserTicket,
+) -> Result<Logged, AppError> {
+    let Some(key) = idempotency_key(headers)? else {
+        return Ok(Logged::Now(log_journey(state, auditor, user_ticket).await?));
+    };
+    let user = user_ticket.user;
+
+    state
+        .db
+        .run(idempotency::Expire {
+            before: models::timestamp(models::now() - IDEMPOTENCY_WINDOW),
+        })
+        .await;
+    let claimed = state
+        .db
+        .query_one(idempotency::Claim {
+            user,
+            key: key.clone(),
+            ticket: user_ticket.id,
+            created_at: models::timestamp(models::now()),
+        })
+        .await;
+
+    if claimed.is_none() {
+        let existing = state
+            .db
+            .query_one(idempotency::Get { user, key })
+            .await
+            // released by a failed first attempt in the meantime
+            .ok_or_else(|| AppError::Conflict(String::from("Please try again.")))?;
+
+        if existing.ticket != user_ticket.id {
+            return Err(AppError::Conflict(String::from(
+                "That Idempotency-Key was already used for a different ticket.",
+            )));
+        }
+        return match existing.response {
+            Some(response) => Ok(Logged::Before(response)),
+            None => Err(AppError::Conflict(String::from(
+                "A request with that Idempotency-Key is still in progress.",
+            ))),
+        };
+    }
+
+    match log_journey(state, auditor, user_ticket).await {
+        Ok(response) => {
+            state
+                .db
+                .run(idempotency::Complete {
+                    user,
+                    key,
+                    response: response.clone(),
+                })
+                .await;
+            Ok(Logged::Now(response))
+        }
+        Err(error) => {
+            state.db.run(idempotency::Release { user, key }).await;
+            Err(error)
+        }
+    }
+}
+
+/// Each tap adds its own row rather than rewriting a counter, so concurrent
+/// taps can't overwrite each other.
+async fn log_journey(
+    state: &State,
+    auditor: &Auditor,
+    mut user_ticket: UserTicket,
+) -> Result<String, AppError> {
+    ensure_valid(state, &user_ticket).await?;
+
+    let logged = state
+        .db
+        .query_one(journey::Insert {
+            ticket: user_ticket.id,
+            taken_at: models::timestamp(models::now()),
+            route: None,
+            stop: None,
+            notes: None,
+        })
+        .await
+        .ok_or_else(|| AppError::internal("inserted journey was not returned"))?;
+    user_ticket.usages = logged.usages;
+    auditor
+        .record(usage_event(
+            Action::UsageIncremented,
+            &user_ticket,
+            user_ticket.usages.saturating_sub(1),
+        ))
+        .await;
+
+    Ok(user_ticket.usages.to_string())
+}
+
+/// The ticket's usage count as the database has it, including changes made
+/// concurrently by other requests.
+pub(crate) async fn usages(state: &State, ticket: TicketId) -> u32 {
+    state
+        .db
+        .query_one(journey::CountFromTicket { ticket })
+        .await
+        .map_or(0, |count| count.count)
+}
+
+/// Journeys can only be logged while a ticket is valid, by the network's
+/// clock. Undoing one is always allowed, to fix mistakes.
+pub(crate) async fn ensure_valid(state: &State, user_ticket: &UserTicket) -> Result<(), AppError> {
+    let format = time::macros::format_description!(
+        "[day padding:none] [month repr:long] [year] at [hour repr:12 padding:none]:[minute][period case:lower]"
+    );
+
+    let def = state
+        .db
+        .query_one(ticket::GetDefinition {
+            id: user_ticket.def,
+        })
+        .await
+        .ok_or_else(|| AppError::internal("ticket refers to a missing definition"))?;
+    let ticket = Ticket::combine(user_ticket.clone(), &def);
+
+    match ticket.validity(models::local_now()) {
+        Validity::Valid => Ok(()),
+        Validity::NotYetValid => Err(AppError::Conflict(format!(
+            "This ticket isn't valid until {}.",
+            ticket.start.format(format).map_err(AppError::internal)?
+        ))),
+        Validity::Expired => Err(AppError::Conflict(format!(
+            "This ticket expired on {}.",
+            ticket.expiry.format(format).map_err(AppError::internal)?
+        ))),
+    }
+}
+
+/// Undoes the most recent journey on the ticket.
+async fn decrement_usage(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> String {
+    let Some(undone) = state
+        .db
+        .query_one(journey::UndoLatest {
+            ticket: user_ticket.id,
+            count: 1,
+        })
+        .await
+    else {
+        // no journeys left to undo
+        return usages(&state, user_ticket.id).await.to_string();
+    };
+    user_ticket.usages = undone.usages;
+
+    auditor
+        .record(usage_event(
+            Action::UsageDecremented,
+            &user_ticket,
+            user_ticket.usages + 1,
+        ))
+        .await;
+
+    user_ticket.usages.to_string()
+}
+
+/// Records a change to a ticket's usage count, from `before` to what it
+/// is now.
+pub(crate) fn usage_event(action: Action, user_ticket: &UserTicket, before: u32) -> Event {
+    Event::new(action)
+        .user(user_ticket.user)
+        .ticket(user_ticket.id)
+        .detail(format!("{before} → {}", user_ticket.usages))
+}
+
+pub(crate) fn ticket_added(user_ticket: &UserTicket) -> Event {
+    let detail = match user_ticket.replaces {
+        Some(replaces) => format!(
+            "definition {}, replacing ticket {replaces}",
+            user_ticket.def
+        ),
+        None => format!("definition {}", user_ticket.def),
+    };
+
+    Event::new(Action::TicketAdded)
+        .user(user_ticket.user)
+        .ticket(user_ticket.id)
+        .detail(detail)
+}
+
+/// A ticket can only be replaced once, so its renewals form a chain.
+///
+/// Only a courtesy before showing the renewal form; [`ticket::Insert`] makes
+/// the check that counts.
+async fn ensure_replaceable(state: &State, user_ticket: &UserTicket) -> Result<(), AppError> {
+    match state
+        .db
+        .query_one(ticket::GetReplacement { id: user_ticket.id })
+        .await
+    {
+        Some(_) => Err(already_replaced()),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn already_replaced() -> AppError {
+    AppError::Conflict(String::from("That ticket has already been replaced."))
+}
+
+/// Whether new tickets can be added for a definition.
+async fn ensure_addable(state: &State, def: DefId) -> Result<(), AppError> {
+    let addable = state
+        .db
+        .query_one(ticket::GetDefinition { id: def })
+        .await
+        .is_some_and(|def| !def.is_retired());
+    if !addable {
+        return Err(AppError::BadRequest(String::from(
+            "That ticket can't be added any more.",
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct CreateTicket {
+    ticket: DefId,
+    qr: String,
+}
+
+async fn add_ticket(
+    AuthUser(user): AuthUser,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(CreateTicket { ticket, qr }): Form<CreateTicket>,
+) -> Result<Redirect, AppError> {
+    ensure_addable(&state, ticket).await?;
+
+    let user_ticket = state
+        .db
+        .query_one(ticket::Insert {
+            user: user.id,
+            def: ticket,
+            qr,
+            replaces: None,
+        })
+        .await
+        .ok_or_else(|| AppError::internal("inserted ticket was not returned"))?;
+    auditor.record(ticket_added(&user_ticket)).await;
+
+    Ok(Redirect::to("/"))
+}
+
+async fn renew_form(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    ensure_replaceable(&state, &user_ticket).await?;
+
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok(markup::renew_form(&ticket, &defs))
+}
+
+/// Adds a ticket in place of a lost one, or the next one of a season. The old
+/// ticket moves to the earlier tickets, keeping its journeys.
+async fn renew_ticket(
+    AuthUser(user): AuthUser,
+    OwnedTicket(old): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    Form(CreateTicket { ticket, qr }): Form<CreateTicket>,
+) -> Result<Redirect, AppError> {
+    ensure_addable(&state, ticket).await?;
+
+    let user_ticket = state
+        .db
+        .query_one(ticket::Insert {
+            user: user.id,
+            def: ticket,
+            qr,
+            replaces: Some(old.id),
+        })
+        .await
+        .ok_or_else(already_replaced)?;
+    auditor.record(ticket_added(&user_ticket)).await;
+
+    Ok(Redirect::to("/"))
+}
+
+async fn get_single_ticket(
+    AuthUser(user): AuthUser,
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    let validation = if user.counts_on_close() {
+        None
+    } else {
+        Some(validate::validation(&state, user_ticket.clone()).await?)
+    };
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok(markup::ticket_card(markup::TicketMarkup::Large {
+        ticket: &ticket,
+        validation,
+    }))
+}
+
+pub(crate) fn tickets_from_defs(
+    user_tickets: impl IntoIterator<Item = UserTicket>,
+    defs: &[TicketDef],
+) -> Result<Vec<Ticket>, AppError> {
+    user_tickets
+        .into_iter()
+        .map(|ut| {
+            let Some(ut_def) = defs.iter().find(|def| def.id == ut.def) else {
+                return Err(AppError::internal(format!(
+                    "ticket {} refers to missing definition {}",
+                    ut.id, ut.def
+                )));
+            };
+            Ok(Ticket::combine(ut, ut_def))
+        })
+        .collect()
+}
@@ -0,0 +1,98 @@
+use axum::{http::HeaderMap, routing::post, Extension, Router};
+use maud::Markup;
+use time::{format_description::well_known::Iso8601, Duration, PrimitiveDateTime};
+
+use super::{log_once, tickets_from_defs, usage_event, OwnedTicket};
+use crate::{
+    audit::Auditor,
+    error::AppError,
+    markup,
+    models::{
+        self,
+        audit::Action,
+        journey,
+        ticket::{self, UserTicket},
+    },
+    State,
+};
+
+/// How long a validation can be undone for from the card. After that it has
+/// to be deleted from the ticket's history.
+const UNDO_WINDOW: Duration = Duration::minutes(2);
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/{ticket}/validate", post(validate).get(validation_page))
+        .route("/{ticket}/validate/undo", post(undo))
+}
+
+/// The validate button, or the confirmation of a recent validation, for the
+/// large card.
+pub(super) async fn validation(state: &State, user_ticket: UserTicket) -> Result<Markup, AppError> {
+    let latest = state
+        .db
+        .query_one(journey::GetLatest {
+            ticket: user_ticket.id,
+        })
+        .await;
+    let undo_left = latest.as_ref().and_then(|latest| {
+        let taken_at = PrimitiveDateTime::parse(&latest.taken_at, &Iso8601::DEFAULT).ok()?;
+        let left = taken_at + UNDO_WINDOW - models::now();
+        left.is_positive().then_some(left)
+    });
+    let defs = state.db.query(ticket::GetAllDefinitions).await;
+    let ticket = tickets_from_defs([user_ticket], &defs)?.remove(0);
+
+    Ok(markup::validation(&ticket, latest.as_ref(), undo_left))
+}
+
+/// Refreshed by the card once the undo window has passed.
+async fn validation_page(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+) -> Result<Markup, AppError> {
+    validation(&state, user_ticket).await
+}
+
+/// Records a journey on purpose, rather than as a side effect of closing the
+/// card.
+async fn validate(
+    OwnedTicket(user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+    headers: HeaderMap,
+) -> Result<Markup, AppError> {
+    log_once(&state, &auditor, &headers, user_ticket.clone()).await?;
+
+    validation(&state, user_ticket).await
+}
+
+async fn undo(
+    OwnedTicket(mut user_ticket): OwnedTicket,
+    Extension(state): Extension<State>,
+    auditor: Auditor,
+) -> Result<Markup, AppError> {
+    let undone = state
+        .db
+        .query_one(journey::UndoRecent {
+            ticket: user_ticket.id,
+            since: models::timestamp(models::now() - UNDO_WINDOW),
+        })
+        .await
+        .ok_or_else(|| {
+            AppError::Conflict(String::from(
+                "It's too late to undo that. You can delete the journey from the ticket's details.",
+            ))
+        })?;
+
+    user_ticket.usages = undone.usages;
+    auditor
+        .record(usage_event(
+            Action::UsageDecremented,
+            &user_ticket,
+            user_ticket.usages + 1,
+        ))
+        .await;
+
+    validation(&state, user_ticket).await
+}
@@ -0,0 +1,37 @@
+//! Jobs run on the cron triggers in `wrangler.toml`, away from any request.
+
+use crate::{
+    audit::{Auditor, Event},
+    models::{self, audit::Action, journey, ticket},
+    State,
+};
+
+/// Removes tickets whose grace period has run out, along with their
+/// journeys.
+pub(crate) async fn purge_deleted_tickets(state: &State, auditor: &Auditor) {
+    let before = models::timestamp(models::now() - ticket::DELETE_GRACE);
+
+    state
+        .db
+        .run(journey::DeleteFromDeletedTickets {
+            before: before.clone(),
+        })
+        .await;
+    state
+        .db
+        .run(ticket::UnlinkDeleted {
+            before: before.clone(),
+        })
+        .await;
+    let purged = state.db.query(ticket::PurgeDeleted { before }).await;
+
+    for user_ticket in purged {
+        auditor
+            .record(
+                Event::new(Action::TicketPurged)
+                    .user(user_ticket.user)
+                    .ticket(user_ticket.id),
+            )
+            .await;
+    }
+}
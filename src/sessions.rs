@@ -1,4 +1,4 @@
-use crate::models::user::User;
+use crate::models::user::{User, UserId};
 
 use worker::{wasm_bindgen_futures, Env};
 
@@ -16,6 +16,10 @@ enum Task {
         key: String,
         result: oneshot::Sender<()>,
     },
+    RemoveAllForUser {
+        user: UserId,
+        result: oneshot::Sender<()>,
+    },
     Close,
 }
 
@@ -41,6 +45,14 @@ impl Sessions {
         finished.await.unwrap();
     }
 
+    /// Signs the user out everywhere. Session ids start with the user's id,
+    /// so their sessions can be found without a separate index.
+    pub async fn remove_all(&self, user: UserId) {
+        let (result, finished) = oneshot::channel();
+        self.send(Task::RemoveAllForUser { user, result }).await;
+        finished.await.unwrap();
+    }
+
     pub async fn close(self) {
         self.send(Task::Close).await;
     }
@@ -74,6 +86,26 @@ pub(crate) fn sessions(env: Env) -> Sessions {
                     rm.await.unwrap();
                     result.send(()).unwrap();
                 }
+                Task::RemoveAllForUser { user, result } => {
+                    let mut cursor = None;
+                    loop {
+                        let mut list = sessions.list().prefix(format!("{user}:"));
+                        if let Some(cursor) = cursor {
+                            list = list.cursor(cursor);
+                        }
+                        let page = list.execute().await.unwrap();
+
+                        for key in page.keys {
+                            sessions.delete(&key.name).await.unwrap();
+                        }
+
+                        if page.list_complete {
+                            break;
+                        }
+                        cursor = page.cursor;
+                    }
+                    result.send(()).unwrap();
+                }
             }
         }
     });
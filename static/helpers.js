@@ -16,8 +16,12 @@ function save_svg(event, index) {
   }
 }
 
-function increment(id) {
-  fetch(`tickets/${id}/inc`, { method: "POST" });
+// `key` is fixed when the ticket is shown, so double clicks count once
+function increment(id, key) {
+  fetch(`tickets/${id}/inc`, {
+    method: "POST",
+    headers: { "Idempotency-Key": key },
+  });
 }
 
 function clearLocalStorage() {